@@ -1,18 +1,44 @@
+use std::fmt;
 use std::collections::HashMap;
 use failure::Error;
 use schema::Schema;
-// use loaders::{CliLoader, DiskLoader, EnvLoader, Loader};
-use loaders::{CliLoader, Loader};
+use layer::{Layer, DEFAULTS_LAYER, OVERRIDES_LAYER};
+use loaders::{CliLoader, DiskLoader, EnvLoader, Loader};
+#[cfg(feature = "serde")]
+use format::Format;
+use source::{Explanation, Provenance, Source};
+use errors::{ConfigErrors, PathError, PathErrorKind};
 use value::{FromValue, IntoValue, Value};
+#[cfg(feature = "serde")]
+use de::ValueDeserializer;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
 
 /// Config collects and contains configuration information.
 /// This is the struct you should be interacting with the most from the unison
 /// crate.
-#[derive(Debug)]
+///
+/// Config values are kept in layers, ordered from the highest precedence to
+/// the lowest. When getting a value, the first layer containing the path
+/// wins. After `Config::init` the layers are as follows:
+///
+/// 1. `overrides` - values set with `Config::set`
+/// 2. one layer per loader, named after it and in the loader order given to
+///    `ConfigBuilder`. With the default loaders these are `cli`, `env`, then
+///    `disk`
+/// 3. `defaults` - the default values of the schema
+///
+/// In other words, defaults < files < env < CLI < overrides. Layers can be
+/// reordered with `Config::move_layer`, and new layers can be added with
+/// `Config::insert_layer`.
 pub struct Config {
   application_name: String,
   schema: Schema,
-  values: Vec<Value>,
+  loaders: Vec<Box<dyn Loader + Send>>,
+  layers: Vec<Layer>,
+  unknown_keys: UnknownKeys,
+  subcommand: Option<String>,
+  trailing_args: Vec<String>,
 }
 
 impl Config {
@@ -20,8 +46,10 @@ impl Config {
   /// This is equivelent to the following:
   ///
   /// ```rust
-  /// let config = Config::new();
-  /// config.init();
+  /// # use unison::*;
+  /// # let schema = Schema::new().build();
+  /// let mut config = Config::new("my_app", schema);
+  /// config.init().unwrap();
   /// ```
   pub fn load(application_name: &str, schema: Schema) -> Result<Self, ConfigError> {
     let mut config = Self::new(application_name, schema);
@@ -29,59 +57,270 @@ impl Config {
     Ok(config)
   }
 
-  /// Creates a new config.
+  /// Creates a new config using the default loaders. See
+  /// `ConfigBuilder::new` for the default loaders and their precedence.
   /// The provided application name will be used by loaders to find
   /// configuration sources intented for your application on the system.
   /// The schema will be used to validate and filter config values.
   pub fn new(application_name: &str, schema: Schema) -> Self {
-    let application_name = application_name.to_owned();
-    Self {
-      application_name,
-      schema,
-      values: Vec::new(),
-    }
+    Self::builder(application_name, schema).build()
+  }
+
+  /// Creates a new config builder which can be used to configure the loaders
+  /// used by the config.
+  pub fn builder(application_name: &str, schema: Schema) -> ConfigBuilder {
+    ConfigBuilder::new(application_name, schema)
   }
 
-  /// Executes all loaders and collects all config values.
+  /// Executes all loaders and collects all config values. Sources which can't
+  /// be read, values which can't be parsed, required paths without a value,
+  /// values failing a validator, and unknown paths when they are denied are
+  /// gathered across every loader and returned together as
+  /// `ConfigError::Errors`.
+  ///
+  /// Calling `init` again collects every value anew. The layers of the
+  /// previous call are replaced, along with any values set with
+  /// `Config::set`.
   pub fn init(&mut self) -> Result<(), ConfigError> {
-    // NOTE: This could be passed in future versons of unison to allow for expantion of the system
-    let loaders: Vec<Box<Loader>> = vec![
-      // Box::new(DiskLoader::new()),
-      // Box::new(EnvLoader::new()),
-      Box::new(CliLoader::new()),
-    ];
-
-    for mut loader in loaders {
-      self
-        .values
-        .push(loader.collect(&self.application_name, &self.schema)?);
-    }
-    // NOTE: This final value is inserted in order to contain any overrides set
-    // using `Config::set`.
-    self.values.push(Value::HashMap(HashMap::new()));
+    self.layers.retain(|l| l.loader().is_none() && l.name() != OVERRIDES_LAYER);
+    let mut errors = Vec::new();
+    self.subcommand = None;
+    self.trailing_args = Vec::new();
+    let mut layers = vec![Layer::new(OVERRIDES_LAYER, Value::HashMap(HashMap::new()))];
+    let mut unknown_keys = Vec::new();
+    for (index, loader) in self.loaders.iter_mut().enumerate() {
+      let mut value = match loader.collect(&self.application_name, &self.schema) {
+        Ok(value) => value,
+        Err(e) => {
+          errors.extend(e.into_path_errors()?);
+          continue;
+        }
+      };
+      unknown_keys.extend(loader.unknown_keys().into_iter().map(|(path, source)| (index, path, source)));
+      if self.subcommand.is_none() {
+        self.subcommand = loader.subcommand();
+      }
+      if self.trailing_args.is_empty() {
+        self.trailing_args = loader.trailing_args();
+      }
+      let mut sources = loader.sources();
+      migrate_deprecated_paths(&self.schema, loader.name(), &mut value, &mut sources);
+      layers.push(Layer::from_loader(loader.name(), value, sources));
+    }
+
+    let defaults_index = self
+      .layers
+      .iter()
+      .position(|l| l.name() == DEFAULTS_LAYER)
+      .unwrap_or(self.layers.len());
+    self.layers.splice(defaults_index..defaults_index, layers);
+
+    for (index, path, source) in unknown_keys {
+      let claimed = match source {
+        Source::CliArg { ref arg, .. } => self
+          .loaders
+          .iter()
+          .enumerate()
+          .any(|(i, l)| i != index && l.claims_arg(arg)),
+        _ => false,
+      };
+      if claimed {
+        continue;
+      }
+      let suggestion = self.schema.suggest_path(&path).map(|p| p.to_owned());
+      let error = PathError::new(path, PathErrorKind::Unknown(suggestion)).with_source(source);
+      match self.unknown_keys {
+        UnknownKeys::Ignore => {}
+        UnknownKeys::Warn => warn!("{}", error),
+        UnknownKeys::Deny => errors.push(error),
+      }
+    }
 
+    let values = self.values();
+    for path in self.schema.path_names() {
+      let value = values.get_path(path);
+      if *value == Value::None {
+        let required = self.schema.path_meta(path).is_some_and(|m| m.required);
+        let enabled = match self.schema.subcommand_of(path) {
+          Some(subcommand) => self.subcommand.as_ref().is_some_and(|s| s == subcommand),
+          None => true,
+        };
+        if required && enabled && !errors.iter().any(|e: &PathError| e.path == path) {
+          errors.push(PathError::new(path, PathErrorKind::Missing));
+        }
+        continue;
+      }
+      for validator in self.schema.path_validators(path) {
+        if let Err(message) = validator.validate(value) {
+          let mut error = PathError::new(path, PathErrorKind::Invalid(message));
+          if let Some(explanation) = self.explain(path) {
+            error = error.with_source(explanation.winner.source);
+          }
+          errors.push(error);
+        }
+      }
+    }
+
+    if !errors.is_empty() {
+      return Err(ConfigError::Errors(ConfigErrors(errors)));
+    }
     Ok(())
   }
 
   /// Gets a value of a given path. If the path exists then the option wrapped
-  /// value will be returned. If no path is found then a none will be returned
-  /// instead.
+  /// value will be returned. If no loader collected a value for the path then
+  /// the schema's default value is used. If no path is found then a none will
+  /// be returned instead.
   pub fn try_get<P: AsRef<str>, T: FromValue>(&self, path: P) -> Option<T> {
     let path = path.as_ref();
-    for value in self.values.iter() {
-      match *value.get_path(path) {
+    for layer in self.layers.iter() {
+      match *layer.value().get_path(path) {
         Value::None => continue,
         ref v => {
-          return match v.to_owned().into_type::<T>() {
-            Ok(v) => Some(v),
-            Err(_) => None,
-          }
+          return v.to_owned().into_type::<T>().ok();
         }
       };
     }
     None
   }
 
+  /// Returns the effective configuration. All layers are merged into a single
+  /// value tree, with values from higher precedence layers replacing those
+  /// from lower precedence layers.
+  pub fn values(&self) -> Value {
+    let mut values = Value::HashMap(HashMap::new());
+    for layer in self.layers.iter().rev() {
+      values.merge(layer.value().to_owned());
+    }
+    values
+  }
+
+  /// Gets the effective value tree at a given path and deserializes it into
+  /// `T`. Hash maps found in several layers are merged, so a struct can be
+  /// filled from values collected by different loaders. An empty path refers
+  /// to the whole configuration.
+  ///
+  /// ```rust
+  /// # extern crate unison;
+  /// # #[macro_use] extern crate serde_derive;
+  /// # use unison::*;
+  /// #[derive(Deserialize)]
+  /// struct Server {
+  ///   port: u32,
+  /// }
+  ///
+  /// # fn main() {
+  /// # let schema = Schema::new().path("server.port", 8000u32).build();
+  /// let config = Config::load("my_app", schema).unwrap();
+  /// let server: Server = config.get_struct("server").unwrap();
+  /// # }
+  /// ```
+  #[cfg(feature = "serde")]
+  pub fn get_struct<P: AsRef<str>, T: DeserializeOwned>(&self, path: P) -> Result<T, ConfigError> {
+    let path = path.as_ref();
+    let values = self.values();
+    let value = if path.is_empty() {
+      values
+    } else {
+      values.get_path(path).to_owned()
+    };
+    T::deserialize(ValueDeserializer::new(value, path)).map_err(|e| ConfigError::Deserialize(e.path, e.message))
+  }
+
+  /// Serializes the effective configuration into the given format. Requires
+  /// the `serde` feature along with the feature of the format.
+  ///
+  /// ```rust
+  /// # use unison::*;
+  /// # let schema = Schema::new().path("db.password", "hunter2").build();
+  /// # let config = Config::load("my_app", schema).unwrap();
+  /// # #[cfg(feature = "toml")]
+  /// # {
+  /// let dump = config.export_redacted(Format::Toml, &["db.password"]).unwrap();
+  /// # assert_eq!(dump, "[db]\npassword = \"[redacted]\"\n");
+  /// # }
+  /// ```
+  #[cfg(feature = "serde")]
+  pub fn export(&self, format: Format) -> Result<String, ConfigError> {
+    self.export_redacted(format, &[])
+  }
+
+  /// Serializes the effective configuration into the given format, replacing
+  /// the value at each of the given paths with `[redacted]`. This should be
+  /// used to keep secrets out of logs and support bundles. Paths marked as
+  /// secret in the schema are always redacted.
+  #[cfg(feature = "serde")]
+  pub fn export_redacted(&self, format: Format, redacted_paths: &[&str]) -> Result<String, ConfigError> {
+    let mut values = self.values();
+    let secret_paths = self
+      .schema
+      .path_names()
+      .into_iter()
+      .filter(|p| self.schema.path_meta(p).is_some_and(|m| m.secret));
+    for path in redacted_paths.iter().cloned().chain(secret_paths) {
+      if *values.get_path(path) != Value::None {
+        values.set_path(path, Value::String(REDACTED.to_owned()));
+      }
+    }
+    format.serialize(&values).map_err(ConfigError::ExportError)
+  }
+
+  /// Explains where the value of a given path came from. The explanation
+  /// contains the value from the winning layer, the loader which produced it,
+  /// and its source, along with every value from other layers that it hid.
+  /// If no layer has a value at the path then a none will be returned. Values
+  /// of paths marked as secret in the schema are redacted.
+  ///
+  /// ```rust
+  /// # use unison::*;
+  /// # let schema = Schema::new().path("server.port", 8000).build();
+  /// # let config = Config::load("my_app", schema).unwrap();
+  /// if let Some(explanation) = config.explain("server.port") {
+  ///   println!("{}", explanation);
+  /// }
+  /// ```
+  pub fn explain<P: AsRef<str>>(&self, path: P) -> Option<Explanation> {
+    let path = path.as_ref();
+    let mut provenances = self.layers.iter().filter_map(|layer| match *layer.value().get_path(path) {
+      Value::None => None,
+      ref v => Some(Provenance {
+        layer: layer.name().to_owned(),
+        loader: layer.loader().map(|l| l.to_owned()),
+        source: layer.source(path),
+        value: self.redact_secrets(path, v.to_owned()),
+      }),
+    });
+
+    Some(Explanation {
+      path: path.to_owned(),
+      winner: provenances.next()?,
+      overridden: provenances.collect(),
+    })
+  }
+
+  /// Replaces the values of secret paths within a value found at a given
+  /// path with `[redacted]`.
+  fn redact_secrets(&self, path: &str, mut value: Value) -> Value {
+    for secret_path in self.schema.path_names() {
+      if !self.schema.path_meta(secret_path).is_some_and(|m| m.secret) {
+        continue;
+      }
+      if secret_path == path {
+        return Value::String(REDACTED.to_owned());
+      }
+      let child_path = match path {
+        "" => Some(secret_path),
+        _ => secret_path.strip_prefix(path).and_then(|p| p.strip_prefix('.')),
+      };
+      if let Some(child_path) = child_path {
+        if *value.get_path(child_path) != Value::None {
+          value.set_path(child_path, Value::String(REDACTED.to_owned()));
+        }
+      }
+    }
+    value
+  }
+
   /// Gets a value of a given path. Panics if the path does not exist.
   pub fn get<P: AsRef<str>, T: FromValue>(&self, path: P) -> T {
     self.try_get(path).unwrap()
@@ -91,10 +330,271 @@ impl Config {
   /// this value will not be presisted to configuration sources.
   pub fn set<P: AsRef<str>, T: IntoValue>(&mut self, path: P, value: T) -> Result<(), ConfigError> {
     let value = value.into_value();
-    let target = self.values.last_mut().ok_or(ConfigError::NotInitialized)?;
-    target.set_path(path, value);
+    let target = self
+      .layers
+      .iter_mut()
+      .find(|l| l.name() == OVERRIDES_LAYER)
+      .ok_or(ConfigError::NotInitialized)?;
+    target.value_mut().set_path(path, value);
     Ok(())
   }
+
+  /// Returns the subcommand given to the CLI, if any. Only the paths in the
+  /// subtree of the given subcommand are required by `Config::init`.
+  pub fn subcommand(&self) -> Option<&str> {
+    self.subcommand.as_deref()
+  }
+
+  /// Returns the arguments given to the CLI after a bare `--`, which are not
+  /// parsed. They are meant to be passed on to another program, such as one
+  /// spawned by the application.
+  pub fn trailing_args(&self) -> &[String] {
+    &self.trailing_args
+  }
+
+  /// Returns the layers of the config, ordered from the highest precedence to
+  /// the lowest.
+  pub fn layers(&self) -> &[Layer] {
+    &self.layers
+  }
+
+  /// Returns the names of the layers of the config, ordered from the highest
+  /// precedence to the lowest.
+  pub fn layer_names(&self) -> Vec<&str> {
+    self.layers.iter().map(|l| l.name()).collect()
+  }
+
+  /// Inserts a new layer at a given index. Index `0` gives the layer the
+  /// highest precedence.
+  pub fn insert_layer<N: Into<String>>(&mut self, index: usize, name: N, value: Value) -> Result<(), ConfigError> {
+    if index > self.layers.len() {
+      return Err(ConfigError::BadLayerIndex(index));
+    }
+    self.layers.insert(index, Layer::new(name, value));
+    Ok(())
+  }
+
+  /// Moves the first layer with the given name to a given index. Index `0`
+  /// gives the layer the highest precedence.
+  pub fn move_layer(&mut self, name: &str, index: usize) -> Result<(), ConfigError> {
+    let current_index = self
+      .layers
+      .iter()
+      .position(|l| l.name() == name)
+      .ok_or_else(|| ConfigError::MissingLayer(name.to_owned()))?;
+    if index >= self.layers.len() {
+      return Err(ConfigError::BadLayerIndex(index));
+    }
+    let layer = self.layers.remove(current_index);
+    self.layers.insert(index, layer);
+    Ok(())
+  }
+}
+
+impl fmt::Debug for Config {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let loader_names: Vec<_> = self.loaders.iter().map(|l| l.name()).collect();
+    f.debug_struct("Config")
+      .field("application_name", &self.application_name)
+      .field("schema", &self.schema)
+      .field("loaders", &loader_names)
+      .field("layers", &self.layers)
+      .field("unknown_keys", &self.unknown_keys)
+      .field("subcommand", &self.subcommand)
+      .field("trailing_args", &self.trailing_args)
+      .finish()
+  }
+}
+
+/// Sets how `Config::init` handles paths which loaders found but which aren't
+/// in the schema, such as a mistyped flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownKeys {
+  /// Unknown paths are dropped silently.
+  Ignore,
+  /// A warning is logged for each unknown path. This is the default.
+  #[default]
+  Warn,
+  /// Each unknown path is reported as an error.
+  Deny,
+}
+
+/// The value which replaces redacted values when exporting a config.
+pub const REDACTED: &str = "[redacted]";
+
+/// Used to configure and build `unison::Config` instances.
+///
+/// Loaders are kept in precedence order. Values collected by a loader take
+/// precedence over values collected by any loader after it. A new builder
+/// starts with the following loaders:
+///
+/// 1. `CliLoader` (`cli`)
+/// 2. `EnvLoader` (`env`)
+/// 3. `DiskLoader` (`disk`)
+///
+/// ```rust
+/// # use unison::*;
+/// # let schema = Schema::new().build();
+/// let config = Config::builder("my_app", schema)
+///   .without_loader("disk")
+///   .loader(DiskLoader::with_path("/etc/company/defaults.toml"))
+///   .build();
+/// ```
+pub struct ConfigBuilder {
+  application_name: String,
+  schema: Schema,
+  loaders: Vec<Box<dyn Loader + Send>>,
+  unknown_keys: UnknownKeys,
+}
+
+impl ConfigBuilder {
+  /// Creates a new config builder with the default loaders.
+  pub fn new(application_name: &str, schema: Schema) -> Self {
+    Self {
+      application_name: application_name.to_owned(),
+      schema,
+      loaders: vec![
+        Box::new(CliLoader::new()),
+        Box::new(EnvLoader::new()),
+        Box::new(DiskLoader::new()),
+      ],
+      unknown_keys: UnknownKeys::default(),
+    }
+  }
+
+  /// Adds a loader after all other loaders, giving it the lowest precedence.
+  pub fn loader<L: Loader + Send + 'static>(mut self, loader: L) -> Self {
+    self.loaders.push(Box::new(loader));
+    self
+  }
+
+  /// Inserts a loader at a given index. Index `0` gives the loader the highest
+  /// precedence. Panics if the index is greater than the number of loaders.
+  pub fn insert_loader<L: Loader + Send + 'static>(mut self, index: usize, loader: L) -> Self {
+    self.loaders.insert(index, Box::new(loader));
+    self
+  }
+
+  /// Removes all loaders with the given name. This can be used to turn off
+  /// any of the default loaders.
+  pub fn without_loader(mut self, name: &str) -> Self {
+    self.loaders.retain(|l| l.name() != name);
+    self
+  }
+
+  /// Removes all loaders, including the default loaders.
+  pub fn without_loaders(mut self) -> Self {
+    self.loaders.clear();
+    self
+  }
+
+  /// Makes every CLI loader read the given arguments instead of
+  /// `std::env::args`, keeping the rest of their settings. The first argument
+  /// is the program name. If there is no CLI loader one is added with the
+  /// highest precedence.
+  pub fn args<I, S>(mut self, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    let args: Vec<String> = args.into_iter().map(|a| a.into()).collect();
+    let mut found = false;
+    for loader in self.loaders.iter_mut().filter(|l| l.name() == "cli") {
+      loader.set_args(args.clone());
+      found = true;
+    }
+    if !found {
+      self.loaders.insert(0, Box::new(CliLoader::new().args(args)));
+    }
+    self
+  }
+
+  /// Makes every env loader read the given variables instead of the process
+  /// environment, keeping the rest of their settings. If there is no env
+  /// loader one is added after the CLI loaders.
+  pub fn vars<I, K, V>(mut self, vars: I) -> Self
+  where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+  {
+    let vars: Vec<(String, String)> = vars.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+    let mut found = false;
+    for loader in self.loaders.iter_mut().filter(|l| l.name() == "env") {
+      loader.set_vars(vars.clone());
+      found = true;
+    }
+    if !found {
+      let index = self.loaders.iter().rposition(|l| l.name() == "cli").map_or(0, |i| i + 1);
+      self.loaders.insert(index, Box::new(EnvLoader::new().vars(vars)));
+    }
+    self
+  }
+
+  /// Sets how paths which aren't in the schema are handled. Defaults to
+  /// `UnknownKeys::Warn`.
+  pub fn unknown_keys(mut self, unknown_keys: UnknownKeys) -> Self {
+    self.unknown_keys = unknown_keys;
+    self
+  }
+
+  /// Builds and returns a config using the loaders set on the builder.
+  pub fn build(self) -> Config {
+    Config {
+      application_name: self.application_name,
+      layers: vec![Layer::new(DEFAULTS_LAYER, self.schema.defaults())],
+      schema: self.schema,
+      loaders: self.loaders,
+      unknown_keys: self.unknown_keys,
+      subcommand: None,
+      trailing_args: Vec::new(),
+    }
+  }
+
+  /// Builds a config and executes all of its loaders at once.
+  pub fn load(self) -> Result<Config, ConfigError> {
+    let mut config = self.build();
+    config.init()?;
+    Ok(config)
+  }
+}
+
+/// Logs a warning for each deprecated path a loader collected a value for,
+/// and moves the value to the replacement path unless the loader collected a
+/// value for it too.
+fn migrate_deprecated_paths(
+  schema: &Schema,
+  loader_name: &str,
+  value: &mut Value,
+  sources: &mut HashMap<String, Source>,
+) {
+  for path in schema.path_names() {
+    let meta = match schema.path_meta(path) {
+      Some(meta) if meta.deprecated => meta,
+      _ => continue,
+    };
+    let deprecated_value = value.get_path(path).to_owned();
+    if deprecated_value == Value::None {
+      continue;
+    }
+
+    match meta.replaced_by {
+      Some(ref replacement) => {
+        warn!(
+          "Config path {} from the {} loader is deprecated, use {} instead",
+          path, loader_name, replacement
+        );
+        if *value.get_path(replacement) == Value::None {
+          value.set_path(path, Value::None);
+          value.set_path(replacement, deprecated_value);
+          if let Some(source) = sources.remove(path) {
+            sources.insert(replacement.to_owned(), source);
+          }
+        }
+      }
+      None => warn!("Config path {} from the {} loader is deprecated", path, loader_name),
+    }
+  }
 }
 
 /// An error type that can be returned from any of the error producing `Config`
@@ -104,7 +604,509 @@ pub enum ConfigError {
   #[fail(display = "Loader Error: {}", _0)] LoaderError(#[fail(cause)] Error),
   #[fail(display = "Not yet initialized. You must call `Config::init` first")] NotInitialized,
   #[fail(display = "Cannot set value. Missing path")] MissingSetPath,
+  #[fail(display = "No layer named {}", _0)] MissingLayer(String),
+  #[fail(display = "Layer index {} is out of bounds", _0)] BadLayerIndex(usize),
   #[fail(display = "Cannot parse value at path {} into type {}", _0, _1)]
   BadPathType(String, &'static str),
+  #[fail(display = "{}", _0)] Errors(ConfigErrors),
+  #[fail(display = "Cannot deserialize value at path {}: {}", _0, _1)] Deserialize(String, String),
+  #[fail(display = "Export Error: {}", _0)] ExportError(#[fail(cause)] Error),
   #[fail(display = "Unknown error")] Unknown,
 }
+
+impl ConfigError {
+  /// Converts errors found at config paths, and loader errors, into a list of
+  /// path errors, so they can be gathered with others. Any other error is
+  /// returned as is.
+  pub(crate) fn into_path_errors(self) -> Result<Vec<PathError>, ConfigError> {
+    match self {
+      ConfigError::BadPathType(path, type_name) => Ok(vec![PathError::new(path, PathErrorKind::BadType(type_name))]),
+      ConfigError::Errors(errors) => Ok(errors.0),
+      ConfigError::LoaderError(e) => Ok(vec![PathError::new("", PathErrorKind::Loader(e.to_string()))]),
+      e => Err(e),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use source::Source;
+
+  struct TestLoader {
+    name: &'static str,
+    values: Value,
+  }
+
+  impl TestLoader {
+    fn new(name: &'static str, path: &str, value: Value) -> Self {
+      let mut values = Value::HashMap(HashMap::new());
+      values.set_path(path, value);
+      Self { name, values }
+    }
+  }
+
+  impl Loader for TestLoader {
+    fn collect(&mut self, _: &str, _: &Schema) -> Result<Value, ConfigError> {
+      Ok(self.values.clone())
+    }
+
+    fn name(&self) -> &str {
+      self.name
+    }
+  }
+
+  fn schema() -> Schema {
+    Schema::new().path::<u32>("server.port", 3000).build()
+  }
+
+  #[test]
+  fn can_build_config_with_default_loaders() {
+    let config = Config::builder("my_app", schema()).build();
+
+    let loader_names: Vec<_> = config.loaders.iter().map(|l| l.name()).collect();
+
+    assert_eq!(loader_names, vec!["cli", "env", "disk"]);
+  }
+
+  #[test]
+  fn can_build_config_without_loaders() {
+    let config = Config::builder("my_app", schema())
+      .without_loader("env")
+      .without_loader("disk")
+      .build();
+
+    let loader_names: Vec<_> = config.loaders.iter().map(|l| l.name()).collect();
+
+    assert_eq!(loader_names, vec!["cli"]);
+  }
+
+  #[test]
+  fn can_get_schema_default_when_no_loader_has_a_value() {
+    let config = Config::builder("my_app", schema())
+      .without_loaders()
+      .load()
+      .unwrap();
+
+    let port: u32 = config.get("server.port");
+
+    assert_eq!(port, 3000);
+  }
+
+  #[test]
+  fn can_order_layers_by_precedence() {
+    let config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(TestLoader::new("cli", "server.port", Value::U32(8000)))
+      .loader(TestLoader::new("env", "server.port", Value::U32(9000)))
+      .load()
+      .unwrap();
+
+    assert_eq!(config.layer_names(), vec!["overrides", "cli", "env", "defaults"]);
+  }
+
+  #[test]
+  fn can_override_loaded_values_with_set() {
+    let mut config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(TestLoader::new("cli", "server.port", Value::U32(8000)))
+      .load()
+      .unwrap();
+
+    config.set("server.port", 9000u32).unwrap();
+    let port: u32 = config.get("server.port");
+
+    assert_eq!(port, 9000);
+  }
+
+  #[test]
+  fn can_init_config_again_without_duplicate_layers() {
+    let mut config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(TestLoader::new("env", "server.port", Value::U32(8000)))
+      .load()
+      .unwrap();
+
+    config.init().unwrap();
+
+    assert_eq!(config.layer_names(), vec!["overrides", "env", "defaults"]);
+  }
+
+  #[test]
+  fn can_insert_and_move_layers() {
+    let mut config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(TestLoader::new("cli", "server.port", Value::U32(8000)))
+      .load()
+      .unwrap();
+    let mut value = Value::HashMap(HashMap::new());
+    value.set_path("server.port", Value::U32(9000));
+
+    config.insert_layer(2, "site", value).unwrap();
+    let port: u32 = config.get("server.port");
+    assert_eq!(port, 8000);
+
+    config.move_layer("site", 0).unwrap();
+    let port: u32 = config.get("server.port");
+    assert_eq!(port, 9000);
+
+    assert_eq!(config.layer_names(), vec!["site", "overrides", "cli", "defaults"]);
+    assert!(config.move_layer("missing", 0).is_err());
+    assert!(config.insert_layer(5, "late", Value::None).is_err());
+  }
+
+  #[test]
+  fn can_explain_where_a_value_came_from() {
+    let mut config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(TestLoader::new("env", "server.port", Value::U32(8000)))
+      .load()
+      .unwrap();
+    config.set("server.port", 9000u32).unwrap();
+
+    let explanation = config.explain("server.port").unwrap();
+
+    assert_eq!(explanation.winner.layer, "overrides");
+    assert_eq!(explanation.winner.loader, None);
+    assert_eq!(explanation.winner.source, Source::Override);
+    assert_eq!(explanation.winner.value, Value::U32(9000));
+    let overridden: Vec<_> = explanation.overridden.iter().map(|p| (&p.layer[..], &p.source)).collect();
+    assert_eq!(overridden, vec![("env", &Source::Unknown), ("defaults", &Source::Default)]);
+    assert_eq!(explanation.overridden[0].loader, Some("env".into()));
+    assert!(config.explain("server.missing").is_none());
+  }
+
+  #[test]
+  fn can_explain_secret_paths_without_their_values() {
+    let schema = Schema::new().path("db.password", "hunter2").secret().build();
+    let config = Config::builder("my_app", schema).without_loaders().load().unwrap();
+
+    let explanation = config.explain("db.password").unwrap();
+    let parent = config.explain("db").unwrap();
+
+    assert_eq!(explanation.winner.value, Value::String(REDACTED.into()));
+    assert_eq!(*parent.winner.value.get_path("password"), Value::String(REDACTED.into()));
+    assert!(!explanation.to_string().contains("hunter2"));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn can_get_struct_merged_from_all_layers() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Server {
+      port: u32,
+      host: String,
+    }
+
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<String>("server.host", "localhost".into())
+      .build();
+    let config = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(TestLoader::new("env", "server.port", Value::U32(8000)))
+      .load()
+      .unwrap();
+
+    let server: Server = config.get_struct("server").unwrap();
+
+    assert_eq!(
+      server,
+      Server {
+        port: 8000,
+        host: "localhost".into(),
+      }
+    );
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn can_name_path_that_failed_to_deserialize() {
+    #[derive(Debug, Deserialize)]
+    struct Server {
+      #[allow(dead_code)]
+      port: String,
+    }
+
+    let config = Config::builder("my_app", schema()).without_loaders().load().unwrap();
+
+    let error = config.get_struct::<_, Server>("server").unwrap_err();
+
+    match error {
+      ConfigError::Deserialize(ref path, _) => assert_eq!(path, "server.port"),
+      e => panic!("unexpected error {}", e),
+    }
+  }
+
+  #[cfg(all(feature = "serde", feature = "json"))]
+  #[test]
+  fn can_export_config_with_redacted_paths() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<String>("db.password", "hunter2".into())
+      .build();
+    let config = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(TestLoader::new("env", "server.port", Value::U32(8000)))
+      .load()
+      .unwrap();
+
+    let json = config
+      .export_redacted(Format::Json, &["db.password", "db.missing"])
+      .unwrap();
+
+    assert_eq!(
+      json,
+      "{\n  \"db\": {\n    \"password\": \"[redacted]\"\n  },\n  \"server\": {\n    \"port\": 8000\n  }\n}"
+    );
+  }
+
+  #[cfg(all(feature = "serde", feature = "json"))]
+  #[test]
+  fn can_export_config_with_secret_paths_redacted() {
+    let schema = Schema::new()
+      .path::<String>("db.password", "hunter2".into())
+      .secret()
+      .build();
+    let config = Config::builder("my_app", schema).without_loaders().load().unwrap();
+
+    let json = config.export(Format::Json).unwrap();
+
+    assert_eq!(json, "{\n  \"db\": {\n    \"password\": \"[redacted]\"\n  }\n}");
+  }
+
+  #[test]
+  fn can_fail_to_load_without_required_path() {
+    let schema = Schema::new().required_path::<String>("db.password").build();
+
+    let result = Config::builder("my_app", schema).without_loaders().load();
+
+    match result {
+      Err(ConfigError::Errors(errors)) => {
+        assert_eq!(errors.0, vec![PathError::new("db.password", PathErrorKind::Missing)])
+      }
+      r => panic!("expected missing required path, got {:?}", r),
+    }
+  }
+
+  #[test]
+  fn can_report_every_validation_error() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .min(1)
+      .max(65535)
+      .path::<String>("log.level", "info".into())
+      .one_of(vec!["debug", "info"])
+      .build();
+
+    let result = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(TestLoader::new("cli", "log.level", Value::String("loud".into())))
+      .loader(TestLoader::new("env", "server.port", Value::U32(99999)))
+      .load();
+
+    match result {
+      Err(ConfigError::Errors(errors)) => assert_eq!(
+        errors.to_string(),
+        "2 config errors:\n  log.level: must be one of debug, info\n  server.port: must be at most 65535"
+      ),
+      r => panic!("expected validation errors, got {:?}", r),
+    }
+  }
+
+  #[test]
+  fn can_deny_unknown_keys_with_suggestion() {
+    let result = Config::builder("my_app", schema())
+      .without_loaders()
+      .vars(vec![("MY_APP_SEVER__PORT", "8000")])
+      .unknown_keys(UnknownKeys::Deny)
+      .load();
+
+    match result {
+      Err(ConfigError::Errors(errors)) => assert_eq!(
+        errors.to_string(),
+        "1 config error:\n  sever.port: unknown config path, did you mean server.port? \
+         (from environment variable `MY_APP_SEVER__PORT`)"
+      ),
+      r => panic!("expected unknown key error, got {:?}", r),
+    }
+  }
+
+  #[test]
+  fn can_load_config_from_given_args_and_vars() {
+    let config = Config::builder("my_app", schema())
+      .without_loader("disk")
+      .args(vec!["my_app", "--server--port", "9000"])
+      .vars(vec![("MY_APP_SERVER__PORT", "8000")])
+      .load()
+      .unwrap();
+
+    assert_eq!(config.get::<_, u32>("server.port"), 9000);
+    assert_eq!(config.layer_names(), vec!["overrides", "cli", "env", "defaults"]);
+  }
+
+  #[test]
+  fn can_require_paths_of_given_subcommand_only() {
+    let schema = || {
+      Schema::new()
+        .subcommand("serve", "Starts the server")
+        .subcommand("check", "Checks a file")
+        .required_path::<String>("check.file")
+        .positional()
+        .path::<u32>("serve.port", 3000)
+        .build()
+    };
+
+    let config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(CliLoader::from_args(vec!["my_app", "serve"]))
+      .load()
+      .unwrap();
+    let result = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(CliLoader::from_args(vec!["my_app", "check"]))
+      .load();
+
+    assert_eq!(config.subcommand(), Some("serve"));
+    match result {
+      Err(ConfigError::Errors(errors)) => assert_eq!(errors.0[0].path, "check.file"),
+      r => panic!("expected missing path error, got {:?}", r),
+    }
+  }
+
+  #[test]
+  fn can_get_trailing_args() {
+    let config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(CliLoader::from_args(vec!["my_app", "--server--port", "9000", "--", "ls", "-la"]))
+      .load()
+      .unwrap();
+
+    assert_eq!(config.get::<_, u32>("server.port"), 9000);
+    assert_eq!(config.trailing_args(), ["ls", "-la"]);
+  }
+
+  #[cfg(feature = "toml")]
+  #[test]
+  fn can_report_bad_file_along_with_errors_of_other_loaders() {
+    use std::env::temp_dir;
+    use std::fs::{remove_file, File};
+    use std::io::Write;
+
+    let path = temp_dir().join("unison_config_bad_file_test.toml");
+    File::create(&path).unwrap().write_all(b"[server\nport = 8000\n").unwrap();
+
+    let result = Config::builder("my_app", schema())
+      .without_loader("cli")
+      .vars(vec![("MY_APP_SERVER__PORT", "x")])
+      .without_loader("disk")
+      .loader(DiskLoader::with_path(&path))
+      .load();
+    remove_file(&path).unwrap();
+
+    let errors = match result {
+      Err(ConfigError::Errors(errors)) => errors,
+      r => panic!("expected errors, got {:?}", r),
+    };
+    assert_eq!(errors.0.len(), 2);
+    assert_eq!(errors.0[0].kind, PathErrorKind::BadType("u32"));
+    match errors.0[1] {
+      PathError {
+        kind: PathErrorKind::Loader(ref message),
+        source: Some(Source::File { path: ref file, .. }),
+        ..
+      } => {
+        assert!(message.starts_with("cannot parse config file"));
+        assert_eq!(*file, path);
+      }
+      ref e => panic!("expected file error, got {:?}", e),
+    }
+  }
+
+  #[test]
+  fn can_give_args_and_vars_to_configured_loaders() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<String>("server.host", "localhost".into())
+      .build();
+
+    let config = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(CliLoader::with_prefix("other"))
+      .loader(EnvLoader::with_prefix("OTHER"))
+      .args(vec!["my_app", "--other-server--port", "9000"])
+      .vars(vec![("OTHER_SERVER__HOST", "example.com")])
+      .load()
+      .unwrap();
+
+    assert_eq!(config.get::<_, u32>("server.port"), 9000);
+    assert_eq!(config.get::<_, String>("server.host"), "example.com");
+    assert_eq!(config.layer_names(), vec!["overrides", "cli", "env", "defaults"]);
+  }
+
+  #[test]
+  fn can_give_args_to_every_cli_loader() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<String>("server.host", "localhost".into())
+      .build();
+
+    let config = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(CliLoader::with_prefix("one"))
+      .loader(CliLoader::with_prefix("two"))
+      .args(vec!["my_app", "--one-server--port=9000", "--two-server--host=example.com"])
+      .load()
+      .unwrap();
+
+    assert_eq!(config.get::<_, u32>("server.port"), 9000);
+    assert_eq!(config.get::<_, String>("server.host"), "example.com");
+  }
+
+  #[test]
+  fn cannot_report_flags_of_other_cli_loaders_as_unknown() {
+    let schema = Schema::new().path::<u32>("port", 3000).build();
+
+    let config = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(CliLoader::new())
+      .loader(CliLoader::with_prefix("other"))
+      .unknown_keys(UnknownKeys::Deny)
+      .args(vec!["my_app", "--port=1", "--other-port=2"])
+      .load()
+      .unwrap();
+
+    assert_eq!(config.get::<_, u32>("port"), 1);
+  }
+
+  #[test]
+  fn can_move_deprecated_path_to_replacement() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<u32>("port", 3000)
+      .replaced_by("server.port")
+      .build();
+    let config = Config::builder("my_app", schema)
+      .without_loaders()
+      .loader(TestLoader::new("env", "port", Value::U32(8000)))
+      .load()
+      .unwrap();
+
+    assert_eq!(config.get::<_, u32>("server.port"), 8000);
+    assert_eq!(config.explain("server.port").unwrap().winner.layer, "env");
+  }
+
+  #[test]
+  fn can_load_config_with_custom_loaders_in_precedence_order() {
+    let config = Config::builder("my_app", schema())
+      .without_loaders()
+      .loader(TestLoader::new("low", "server.port", Value::U32(8000)))
+      .insert_loader(0, TestLoader::new("high", "server.port", Value::U32(9000)))
+      .load()
+      .unwrap();
+
+    let port: u32 = config.get("server.port");
+
+    assert_eq!(port, 9000);
+  }
+}
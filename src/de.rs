@@ -0,0 +1,323 @@
+use std::fmt;
+use std::error;
+use std::collections::hash_map;
+use serde::de::{self, Deserializer, IntoDeserializer, Visitor};
+use serde::de::value::MapAccessDeserializer;
+use value::Value;
+
+/// Deserializes a value tree into any type implementing `serde::Deserialize`.
+/// The deserializer keeps track of the config path of the value it holds so
+/// errors can name the path that failed.
+pub struct ValueDeserializer {
+  value: Value,
+  path: String,
+}
+
+impl ValueDeserializer {
+  /// Creates a new deserializer for a value found at the given config path.
+  pub fn new<P: Into<String>>(value: Value, path: P) -> Self {
+    Self {
+      value,
+      path: path.into(),
+    }
+  }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+  if path.is_empty() {
+    key.to_owned()
+  } else {
+    format!("{}.{}", path, key)
+  }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+  type Error = DeserializeError;
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+    let path = self.path.clone();
+    let result = match self.value {
+      Value::Bool(b) => visitor.visit_bool(b),
+
+      Value::USize(n) => visitor.visit_u64(n as u64),
+      Value::ISize(n) => visitor.visit_i64(n as i64),
+
+      Value::U8(n) => visitor.visit_u8(n),
+      Value::U16(n) => visitor.visit_u16(n),
+      Value::U32(n) => visitor.visit_u32(n),
+      Value::U64(n) => visitor.visit_u64(n),
+      #[cfg(feature = "int128")]
+      Value::U128(n) => visitor.visit_u128(n),
+
+      Value::I8(n) => visitor.visit_i8(n),
+      Value::I16(n) => visitor.visit_i16(n),
+      Value::I32(n) => visitor.visit_i32(n),
+      Value::I64(n) => visitor.visit_i64(n),
+      #[cfg(feature = "int128")]
+      Value::I128(n) => visitor.visit_i128(n),
+
+      Value::F32(n) => visitor.visit_f32(n),
+      Value::F64(n) => visitor.visit_f64(n),
+
+      Value::String(s) => visitor.visit_string(s),
+
+      Value::HashMap(h) => visitor.visit_map(MapAccess {
+        iter: h.into_iter(),
+        value: None,
+        path: self.path,
+      }),
+      Value::Vec(v) => visitor.visit_seq(SeqAccess {
+        iter: v.into_iter().enumerate(),
+        path: self.path,
+      }),
+
+      Value::None => visitor.visit_unit(),
+    };
+    result.map_err(|e| e.at_path(&path))
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+    match self.value {
+      Value::None => visitor.visit_none(),
+      _ => visitor.visit_some(self),
+    }
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    let path = self.path.clone();
+    let result = match self.value {
+      Value::String(s) => visitor.visit_enum(s.into_deserializer()),
+      Value::HashMap(h) => visitor.visit_enum(MapAccessDeserializer::new(MapAccess {
+        iter: h.into_iter(),
+        value: None,
+        path: self.path,
+      })),
+      _ => return self.deserialize_any(visitor),
+    };
+    result.map_err(|e| e.at_path(&path))
+  }
+
+  forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+    identifier ignored_any
+  }
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for Value {
+  type Deserializer = ValueDeserializer;
+
+  fn into_deserializer(self) -> Self::Deserializer {
+    ValueDeserializer::new(self, "")
+  }
+}
+
+impl<'de> Deserializer<'de> for Value {
+  type Error = DeserializeError;
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+    self.into_deserializer().deserialize_any(visitor)
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+    self.into_deserializer().deserialize_option(visitor)
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.into_deserializer().deserialize_newtype_struct(name, visitor)
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    name: &'static str,
+    variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Self::Error> {
+    self.into_deserializer().deserialize_enum(name, variants, visitor)
+  }
+
+  forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+    bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+    identifier ignored_any
+  }
+}
+
+struct MapAccess {
+  iter: hash_map::IntoIter<String, Value>,
+  value: Option<(String, Value)>,
+  path: String,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess {
+  type Error = DeserializeError;
+
+  fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+    match self.iter.next() {
+      Some((key, value)) => {
+        let key_deserializer: de::value::StrDeserializer<DeserializeError> = key.as_str().into_deserializer();
+        let key_value = seed.deserialize(key_deserializer)?;
+        self.value = Some((key, value));
+        Ok(Some(key_value))
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+    let (key, value) = self
+      .value
+      .take()
+      .ok_or_else(|| de::Error::custom("value requested before key"))?;
+    seed.deserialize(ValueDeserializer::new(value, child_path(&self.path, &key)))
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.iter.len())
+  }
+}
+
+struct SeqAccess {
+  iter: ::std::iter::Enumerate<::std::vec::IntoIter<Value>>,
+  path: String,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess {
+  type Error = DeserializeError;
+
+  fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error> {
+    match self.iter.next() {
+      Some((index, value)) => {
+        let path = child_path(&self.path, &index.to_string());
+        seed.deserialize(ValueDeserializer::new(value, path)).map(Some)
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.iter.len())
+  }
+}
+
+/// An error produced when a value tree cannot be deserialized. The path is
+/// the config path of the value which failed. Items within a list are
+/// addressed by their index, for example `server.hosts.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeserializeError {
+  pub path: String,
+  pub message: String,
+}
+
+impl DeserializeError {
+  fn at_path(mut self, path: &str) -> Self {
+    if self.path.is_empty() {
+      self.path = path.to_owned();
+    }
+    self
+  }
+}
+
+impl fmt::Display for DeserializeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.path.is_empty() {
+      write!(f, "{}", self.message)
+    } else {
+      write!(f, "{} at path {}", self.message, self.path)
+    }
+  }
+}
+
+impl error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+  fn custom<T: fmt::Display>(message: T) -> Self {
+    Self {
+      path: String::new(),
+      message: message.to_string(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+  use serde::Deserialize;
+
+  #[derive(Debug, PartialEq, Deserialize)]
+  struct Server {
+    port: u16,
+    host: Option<String>,
+    mode: Mode,
+    peers: Vec<String>,
+  }
+
+  #[derive(Debug, PartialEq, Deserialize)]
+  #[serde(rename_all = "lowercase")]
+  enum Mode {
+    Fast,
+    Safe,
+  }
+
+  fn server_value() -> Value {
+    let mut value = Value::HashMap(HashMap::new());
+    value.set_path("port", Value::U32(8000));
+    value.set_path("mode", Value::String("safe".into()));
+    value.set_path("peers", Value::Vec(vec![Value::String("a".into())]));
+    value
+  }
+
+  #[test]
+  fn can_deserialize_value_into_struct() {
+    let server = Server::deserialize(server_value()).unwrap();
+
+    assert_eq!(
+      server,
+      Server {
+        port: 8000,
+        host: None,
+        mode: Mode::Safe,
+        peers: vec!["a".into()],
+      }
+    );
+  }
+
+  #[test]
+  fn can_name_path_of_value_which_failed() {
+    let mut value = server_value();
+    value.set_path("peers", Value::Vec(vec![Value::U32(1)]));
+
+    let error = Server::deserialize(ValueDeserializer::new(value, "server")).unwrap_err();
+
+    assert_eq!(error.path, "server.peers.0");
+  }
+
+  #[test]
+  fn can_name_path_of_struct_missing_a_field() {
+    let mut value = Value::HashMap(HashMap::new());
+    value.set_path("mode", Value::String("fast".into()));
+
+    let error = Server::deserialize(ValueDeserializer::new(value, "server")).unwrap_err();
+
+    assert_eq!(error.path, "server");
+    assert_eq!(error.message, "missing field `port`");
+  }
+}
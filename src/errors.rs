@@ -0,0 +1,122 @@
+use std::fmt;
+use source::Source;
+
+/// Every error found at a config path while loading a config. Returned within
+/// `ConfigError::Errors` so all of them can be fixed at once, rather than one
+/// per run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigErrors(pub Vec<PathError>);
+
+impl ConfigErrors {
+  /// Returns an iterator over the errors.
+  pub fn iter(&self) -> ::std::slice::Iter<'_, PathError> {
+    self.0.iter()
+  }
+}
+
+impl fmt::Display for ConfigErrors {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.0.len() {
+      1 => write!(f, "1 config error:")?,
+      n => write!(f, "{} config errors:", n)?,
+    }
+    for error in self.0.iter() {
+      write!(f, "\n  {}", error)?;
+    }
+    Ok(())
+  }
+}
+
+/// An error found at a config path, along with where the offending value
+/// came from when it is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathError {
+  pub path: String,
+  pub kind: PathErrorKind,
+  pub source: Option<Source>,
+}
+
+impl PathError {
+  /// Creates a new path error without a source.
+  pub fn new<P: Into<String>>(path: P, kind: PathErrorKind) -> Self {
+    Self {
+      path: path.into(),
+      kind,
+      source: None,
+    }
+  }
+
+  /// Sets the source of the error, unless the source is unknown.
+  pub fn with_source(mut self, source: Source) -> Self {
+    self.source = match source {
+      Source::Unknown => None,
+      source => Some(source),
+    };
+    self
+  }
+}
+
+impl fmt::Display for PathError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.path.as_str() {
+      "" => write!(f, "{}", self.kind)?,
+      path => write!(f, "{}: {}", path, self.kind)?,
+    }
+    if let Some(ref source) = self.source {
+      write!(f, " (from {})", source)?;
+    }
+    Ok(())
+  }
+}
+
+/// The kinds of errors found at a config path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathErrorKind {
+  /// The value could not be parsed into the type of the path.
+  BadType(&'static str),
+  /// The path is not in the schema. Holds the closest schema path, if any.
+  Unknown(Option<String>),
+  /// The path is required but no loader collected a value for it.
+  Missing,
+  /// The value failed a validator. Holds the validator's message.
+  Invalid(String),
+  /// A loader failed to read its source, such as a config file which can't
+  /// be parsed. Holds the loader's message. These errors have an empty path.
+  Loader(String),
+}
+
+impl fmt::Display for PathErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      PathErrorKind::BadType(type_name) => write!(f, "cannot parse value into type {}", type_name),
+      PathErrorKind::Unknown(Some(ref suggestion)) => {
+        write!(f, "unknown config path, did you mean {}?", suggestion)
+      }
+      PathErrorKind::Unknown(None) => write!(f, "unknown config path"),
+      PathErrorKind::Missing => write!(f, "missing value for required path"),
+      PathErrorKind::Invalid(ref message) | PathErrorKind::Loader(ref message) => write!(f, "{}", message),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn can_display_every_error_with_its_source() {
+    let errors = ConfigErrors(vec![
+      PathError::new("server.port", PathErrorKind::BadType("u32")).with_source(Source::EnvVar {
+        name: "MY_APP_SERVER__PORT".into(),
+      }),
+      PathError::new("db.password", PathErrorKind::Missing).with_source(Source::Unknown),
+    ]);
+
+    assert_eq!(
+      errors.to_string(),
+      "2 config errors:\n  \
+       server.port: cannot parse value into type u32 (from environment variable `MY_APP_SERVER__PORT`)\n  \
+       db.password: missing value for required path"
+    );
+  }
+}
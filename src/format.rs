@@ -0,0 +1,212 @@
+use std::path::Path;
+#[cfg(feature = "ini")]
+use std::collections::HashMap;
+use failure::Error;
+use value::Value;
+
+#[cfg(feature = "toml")]
+use toml;
+#[cfg(feature = "json")]
+use serde_json;
+#[cfg(feature = "yaml")]
+use serde_yaml;
+#[cfg(feature = "ini")]
+use ini;
+
+/// The formats configuration files can be written in. Each format is only
+/// available when its cargo feature of the same name is enabled. `toml` is
+/// enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Toml,
+  Json,
+  Yaml,
+  Ini,
+}
+
+impl Format {
+  /// All formats in the order configuration files are searched for.
+  pub fn all() -> Vec<Format> {
+    vec![Format::Toml, Format::Json, Format::Yaml, Format::Ini]
+  }
+
+  /// Returns the file extensions used by the format.
+  pub fn extensions(&self) -> &'static [&'static str] {
+    match *self {
+      Format::Toml => &["toml"],
+      Format::Json => &["json"],
+      Format::Yaml => &["yaml", "yml"],
+      Format::Ini => &["ini"],
+    }
+  }
+
+  /// Returns the option wrapped format matching the extension of the given
+  /// path if there is one.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+    let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+    Self::all()
+      .into_iter()
+      .find(|f| f.extensions().contains(&extension.as_str()))
+  }
+
+  /// Returns a boolean indicating if support for the format was compiled in.
+  pub fn is_enabled(&self) -> bool {
+    match *self {
+      Format::Toml => cfg!(feature = "toml"),
+      Format::Json => cfg!(feature = "json"),
+      Format::Yaml => cfg!(feature = "yaml"),
+      Format::Ini => cfg!(feature = "ini"),
+    }
+  }
+
+  /// Parses the given source into a loosely typed value tree. Numbers are
+  /// parsed as `i64`, `u64`, or `f64` and should be cast using the schema
+  /// afterwards.
+  #[allow(unused_variables)]
+  pub fn parse(&self, source: &str) -> Result<Value, Error> {
+    match *self {
+      #[cfg(feature = "toml")]
+      Format::Toml => Ok(from_toml(source.parse()?)),
+      #[cfg(feature = "json")]
+      Format::Json => Ok(from_json(serde_json::from_str(source)?)),
+      #[cfg(feature = "yaml")]
+      Format::Yaml => Ok(from_yaml(serde_yaml::from_str(source)?)),
+      #[cfg(feature = "ini")]
+      Format::Ini => Ok(from_ini(ini::Ini::load_from_str(source)?)),
+      #[allow(unreachable_patterns)]
+      f => Err(FormatError::NotEnabled(f).into()),
+    }
+  }
+
+  /// Serializes the given value tree into the format. Requires the `serde`
+  /// feature. INI files cannot be serialized.
+  #[cfg(feature = "serde")]
+  #[allow(unused_variables)]
+  pub fn serialize(&self, value: &Value) -> Result<String, Error> {
+    match *self {
+      #[cfg(feature = "toml")]
+      Format::Toml => Ok(toml::to_string(value)?),
+      #[cfg(feature = "json")]
+      Format::Json => Ok(serde_json::to_string_pretty(value)?),
+      #[cfg(feature = "yaml")]
+      Format::Yaml => Ok(serde_yaml::to_string(value)?),
+      Format::Ini => Err(FormatError::CannotSerialize(Format::Ini).into()),
+      #[allow(unreachable_patterns)]
+      f => Err(FormatError::NotEnabled(f).into()),
+    }
+  }
+}
+
+#[cfg(feature = "toml")]
+fn from_toml(value: toml::Value) -> Value {
+  match value {
+    toml::Value::String(s) => Value::String(s),
+    toml::Value::Integer(n) => Value::I64(n),
+    toml::Value::Float(n) => Value::F64(n),
+    toml::Value::Boolean(b) => Value::Bool(b),
+    toml::Value::Datetime(d) => Value::String(d.to_string()),
+    toml::Value::Array(a) => Value::Vec(a.into_iter().map(from_toml).collect()),
+    toml::Value::Table(t) => Value::HashMap(t.into_iter().map(|(k, v)| (k, from_toml(v))).collect()),
+  }
+}
+
+#[cfg(feature = "json")]
+fn from_json(value: serde_json::Value) -> Value {
+  match value {
+    serde_json::Value::Null => Value::None,
+    serde_json::Value::Bool(b) => Value::Bool(b),
+    serde_json::Value::Number(n) => {
+      if let Some(n) = n.as_i64() {
+        Value::I64(n)
+      } else if let Some(n) = n.as_u64() {
+        Value::U64(n)
+      } else {
+        Value::F64(n.as_f64().unwrap_or(0.0))
+      }
+    }
+    serde_json::Value::String(s) => Value::String(s),
+    serde_json::Value::Array(a) => Value::Vec(a.into_iter().map(from_json).collect()),
+    serde_json::Value::Object(o) => Value::HashMap(o.into_iter().map(|(k, v)| (k, from_json(v))).collect()),
+  }
+}
+
+#[cfg(feature = "yaml")]
+fn from_yaml(value: serde_yaml::Value) -> Value {
+  match value {
+    serde_yaml::Value::Null => Value::None,
+    serde_yaml::Value::Bool(b) => Value::Bool(b),
+    serde_yaml::Value::Number(n) => {
+      if let Some(n) = n.as_i64() {
+        Value::I64(n)
+      } else if let Some(n) = n.as_u64() {
+        Value::U64(n)
+      } else {
+        Value::F64(n.as_f64().unwrap_or(0.0))
+      }
+    }
+    serde_yaml::Value::String(s) => Value::String(s),
+    serde_yaml::Value::Sequence(s) => Value::Vec(s.into_iter().map(from_yaml).collect()),
+    serde_yaml::Value::Mapping(m) => Value::HashMap(
+      m.into_iter()
+        .filter_map(|(k, v)| {
+          let key = match k {
+            serde_yaml::Value::String(s) => s,
+            serde_yaml::Value::Number(n) => n.to_string(),
+            serde_yaml::Value::Bool(b) => b.to_string(),
+            _ => return None,
+          };
+          Some((key, from_yaml(v)))
+        })
+        .collect(),
+    ),
+  }
+}
+
+/// Sections map to tables. Dots within section names are treated as nested
+/// tables, so `port` within a `[server.tls]` section becomes
+/// `server.tls.port`. All values are strings.
+#[cfg(feature = "ini")]
+fn from_ini(value: ini::Ini) -> Value {
+  let mut values = Value::HashMap(HashMap::new());
+  for (section, properties) in value {
+    for (key, value) in properties {
+      let path = match section {
+        Some(ref section) => format!("{}.{}", section, key),
+        None => key,
+      };
+      values.set_path(path, Value::String(value));
+    }
+  }
+  values
+}
+
+#[derive(Debug, Fail)]
+pub enum FormatError {
+  #[fail(display = "Support for the {:?} format is not enabled", _0)] NotEnabled(Format),
+  #[fail(display = "Values cannot be serialized into the {:?} format", _0)] CannotSerialize(Format),
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn can_get_format_from_path() {
+    assert_eq!(Format::from_path("my_app.toml"), Some(Format::Toml));
+    assert_eq!(Format::from_path("my_app.YML"), Some(Format::Yaml));
+    assert_eq!(Format::from_path("my_app.txt"), None);
+  }
+
+  #[cfg(all(feature = "toml", feature = "json", feature = "yaml", feature = "ini"))]
+  #[test]
+  fn can_parse_same_value_tree_from_all_formats() {
+    let toml = Format::Toml.parse("[server]\nport = \"8000\"\n").unwrap();
+    let json = Format::Json.parse("{\"server\": {\"port\": \"8000\"}}").unwrap();
+    let yaml = Format::Yaml.parse("server:\n  port: \"8000\"\n").unwrap();
+    let ini = Format::Ini.parse("[server]\nport = 8000\n").unwrap();
+
+    assert_eq!(toml, json);
+    assert_eq!(toml, yaml);
+    assert_eq!(toml, ini);
+  }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use source::Source;
+use value::Value;
+
+/// The name of the layer holding overrides set with `Config::set`.
+pub const OVERRIDES_LAYER: &str = "overrides";
+
+/// The name of the layer holding the default values of the schema.
+pub const DEFAULTS_LAYER: &str = "defaults";
+
+/// A layer of config values collected from a single source. Layers are
+/// stacked within a `Config`, and a value in one layer hides values at the
+/// same path in every layer with a lower precedence.
+#[derive(Debug, Clone)]
+pub struct Layer {
+  name: String,
+  loader: Option<String>,
+  value: Value,
+  sources: HashMap<String, Source>,
+}
+
+impl Layer {
+  /// Creates a new layer with a given name and value tree.
+  pub fn new<N: Into<String>>(name: N, value: Value) -> Self {
+    Self {
+      name: name.into(),
+      loader: None,
+      value,
+      sources: HashMap::new(),
+    }
+  }
+
+  pub(crate) fn from_loader(name: &str, value: Value, sources: HashMap<String, Source>) -> Self {
+    Self {
+      name: name.to_owned(),
+      loader: Some(name.to_owned()),
+      value,
+      sources,
+    }
+  }
+
+  /// Returns the name of the layer.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// Returns the option wrapped name of the loader which produced the layer.
+  /// Layers inserted with `Config::insert_layer`, and the overrides and
+  /// defaults layers, have no loader.
+  pub fn loader(&self) -> Option<&str> {
+    self.loader.as_deref()
+  }
+
+  /// Returns the value tree of the layer.
+  pub fn value(&self) -> &Value {
+    &self.value
+  }
+
+  pub(crate) fn value_mut(&mut self) -> &mut Value {
+    &mut self.value
+  }
+
+  /// Returns the source of the value at a given path. If the layer has no
+  /// source for the path itself then the source of the nearest parent or
+  /// child path is used instead.
+  pub fn source(&self, path: &str) -> Source {
+    let mut parent_path = path;
+    loop {
+      if let Some(source) = self.sources.get(parent_path) {
+        return source.clone();
+      }
+      match parent_path.rfind('.') {
+        Some(index) => parent_path = &parent_path[..index],
+        None => break,
+      }
+    }
+
+    let child_prefix = format!("{}.", path);
+    let mut child_paths: Vec<_> = self
+      .sources
+      .keys()
+      .filter(|p| p.starts_with(&child_prefix))
+      .collect();
+    child_paths.sort();
+    if let Some(child_path) = child_paths.first() {
+      return self.sources[*child_path].clone();
+    }
+
+    match self.name.as_str() {
+      DEFAULTS_LAYER => Source::Default,
+      OVERRIDES_LAYER => Source::Override,
+      _ => Source::Unknown,
+    }
+  }
+}
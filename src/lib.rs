@@ -1,12 +1,55 @@
+// `failure_derive` expands `#[derive(Fail)]` into impls nested inside constants.
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate failure;
+#[macro_use]
+extern crate log;
+#[cfg(feature = "ini")]
+extern crate ini;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "toml")]
+extern crate toml;
+#[cfg(feature = "derive")]
+extern crate unison_derive;
 
 mod loaders;
 mod config;
+#[cfg(feature = "serde")]
+mod de;
+mod errors;
+mod format;
+mod layer;
 mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod source;
+mod validate;
 mod value;
+mod value_type;
 
 pub use self::loaders::*;
 pub use self::config::*;
+#[cfg(feature = "serde")]
+pub use self::de::*;
+pub use self::errors::*;
+pub use self::format::*;
+pub use self::layer::*;
 pub use self::schema::*;
-pub use self::value::*;
\ No newline at end of file
+pub use self::source::*;
+pub use self::validate::*;
+pub use self::value::*;
+pub use self::value_type::*;
+#[cfg(feature = "derive")]
+pub use unison_derive::{FromValue, IntoValue, UnisonSchema};
@@ -1,72 +1,902 @@
 use std::env::args;
+use std::process;
 use std::collections::HashMap;
 use config::ConfigError;
+use errors::ConfigErrors;
+use source::Source;
 use value::Value;
+use loaders::env::EnvLoader;
 use loaders::loader::Loader;
 use schema::Schema;
 
 /// Loads config values from command line flags. flags are mapped to config
 /// paths by replacing `--` with `.`. Flags must start with `--`.
 /// For example, `--server--port=8000` becomes `server.port` with a value of
-/// `800` (parsed into the expected type provided by the schema). Note that
-/// if a suffix is used then the suffix will be expected before the flag:
-/// `suffix--server--port=8000`.
+/// `800` (parsed into the expected type provided by the schema). The value
+/// may also be given as the next argument, as in `--server--port 8000`.
+/// Flags of paths which can be given without a value, such as bools, never
+/// take the next argument.
+///
+/// A prefix can be set to namespace the flags of the loader, so several
+/// components can share one command line. With a prefix of `myapp` the flag
+/// becomes `--myapp-server--port=8000`, and flags without the prefix are left
+/// alone.
+///
+/// Lists can be given as a comma separated value, by repeating the flag, or
+/// both: `--hosts=a,b --hosts=c` becomes `["a", "b", "c"]`.
+///
+/// Maps are given as comma separated `key=value` entries, by repeating the
+/// flag, or with a flag per entry: `--labels=env=prod --labels--team=core`
+/// becomes a map with both entries.
+///
+/// Paths with a short alias set in the schema can also be given as `-p 8000`
+/// or `-p8000`. Short flags without values can be combined, so `-vq` is the
+/// same as `-v -q`.
+///
+/// Arguments which aren't flags are bound to the positional paths of the
+/// schema in order, so `app in.txt` sets the first positional path to
+/// `in.txt`. When the last positional path is a list it takes every
+/// remaining argument. The first argument naming a subcommand of the schema
+/// selects it, which enables the paths of its subtree: `app serve --port
+/// 8000` sets `serve.port`, and `serve`'s positional paths follow the others.
+/// Paths of subcommands which weren't given are reported as unknown keys.
+///
+/// Short flags, positional arguments, and subcommands are only read by
+/// loaders without a prefix.
+///
+/// Parsing stops at a bare `--`. The arguments after it are left as they are
+/// and returned by `Loader::trailing_args`, so they can be passed on to
+/// another program.
+///
+/// When the help flag, `--help` by default or `--myapp-help` with a prefix,
+/// is given the loader prints help generated from the schema and exits the
+/// process.
 pub struct CliLoader {
-  suffix: Option<String>,
+  flag_prefix: String,
+  args: Option<Vec<String>>,
+  help_flag: Option<String>,
+  sources: HashMap<String, Source>,
+  unknown_keys: Vec<(String, Source)>,
+  subcommand: Option<String>,
+  trailing_args: Vec<String>,
 }
 
 impl CliLoader {
-  /// Create a new Cli loader without a suffix.
+  /// Create a new Cli loader without a prefix.
   pub fn new() -> Self {
-    Self { suffix: None }
+    Self::with_flag_prefix("--".into(), "--help".into())
   }
 
-  /// Create a new Cli loader with a given suffix. The suffix will be expected
-  /// at the start of all flags. It is recommended your suffix start with
-  /// `--`.
+  /// Create a new Cli loader with a given prefix. The prefix will be expected
+  /// at the start of all flags, after `--` and followed by `-`. For example a
+  /// prefix of `myapp` will match `--myapp-server--port`.
+  pub fn with_prefix(prefix: &str) -> Self {
+    let prefix = prefix.trim_matches('-').to_lowercase();
+    let flag_prefix = format!("--{}-", prefix);
+    let help_flag = format!("{}help", flag_prefix);
+    Self::with_flag_prefix(flag_prefix, help_flag)
+  }
+
+  /// Create a new Cli loader with a given suffix. The suffix is expected at
+  /// the start of all flags, followed by `--`, as in
+  /// `suffix--server--port=8000`.
+  #[deprecated(note = "the suffix is a prefix, use `CliLoader::with_prefix` instead")]
   pub fn with_suffix(suffix: &str) -> Self {
+    let flag_prefix = format!("{}--", suffix.to_lowercase());
+    let help_flag = format!("{}help", flag_prefix);
+    Self::with_flag_prefix(flag_prefix, help_flag)
+  }
+
+  fn with_flag_prefix(flag_prefix: String, help_flag: String) -> Self {
     Self {
-      suffix: Some(suffix.to_lowercase()),
+      flag_prefix,
+      args: None,
+      help_flag: Some(help_flag),
+      sources: HashMap::new(),
+      unknown_keys: Vec::new(),
+      subcommand: None,
+      trailing_args: Vec::new(),
+    }
+  }
+
+  /// Create a new Cli loader without a prefix which reads the given arguments
+  /// instead of `std::env::args`. See `CliLoader::args`.
+  pub fn from_args<I, S>(args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    Self::new().args(args)
+  }
+
+  /// Sets the arguments the loader reads instead of `std::env::args`. Like
+  /// `std::env::args`, the first argument is expected to be the program name
+  /// and is skipped.
+  pub fn args<I, S>(mut self, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    self.set_args(args.into_iter().map(|a| a.into()).collect());
+    self
+  }
+
+  /// Sets the flag which prints help and exits. Defaults to `--help`, or
+  /// `--<prefix>-help` with a prefix.
+  pub fn help_flag(mut self, flag: &str) -> Self {
+    self.help_flag = Some(flag.to_owned());
+    self
+  }
+
+  /// Disables the help flag.
+  pub fn without_help(mut self) -> Self {
+    self.help_flag = None;
+    self
+  }
+
+  /// Generates help text listing the subcommands and positional arguments of
+  /// the schema, and the flag of every schema path along with its type,
+  /// default, description, and environment variables. The variables
+  /// are named as the default `EnvLoader` names them.
+  pub fn help(&self, application_name: &str, schema: &Schema) -> String {
+    let env_loader = EnvLoader::new();
+    let defaults = schema.defaults();
+    let positionals = schema.positional_paths();
+    let subcommands = schema.subcommands();
+
+    let mut help = format!("Usage: {} [OPTIONS]", application_name);
+    if !subcommands.is_empty() {
+      help += " [COMMAND]";
+    }
+    for path in positionals.iter().filter(|p| schema.subcommand_of(p).is_none()) {
+      help += &format!(" {}", positional_name(path, schema));
+    }
+    help += "\n\n";
+
+    if !subcommands.is_empty() {
+      help += "Commands:\n";
+      for (name, description) in subcommands {
+        help += &format!("  {}\n      {}\n", name, description);
+      }
+      help += "\n";
+    }
+
+    if !positionals.is_empty() {
+      help += "Arguments:\n";
+      for path in positionals {
+        match schema.subcommand_of(path) {
+          Some(subcommand) => help += &format!("  {} {}\n", subcommand, positional_name(path, schema)),
+          None => help += &format!("  {}\n", positional_name(path, schema)),
+        }
+        if let Some(description) = schema.path_meta(path).and_then(|m| m.description.as_ref()) {
+          help += &format!("      {}\n", description);
+        }
+      }
+      help += "\n";
+    }
+
+    help += "Options:\n";
+
+    for path in schema.path_names() {
+      let meta = schema.path_meta(path).cloned().unwrap_or_default();
+      let negatable = schema.path_type(path).is_some_and(|t| t.negated_flag_value().is_some());
+      let flag = match self.flag_name(path) {
+        ref flag if negatable => format!("{}[no-]{}", self.flag_prefix, &flag[self.flag_prefix.len()..]),
+        flag => flag,
+      };
+      match meta.short {
+        Some(short) => help += &format!("  -{}, {}", short, flag),
+        None => help += &format!("  {}", flag),
+      }
+      match schema.path_type(path) {
+        Some(t) if t.flag_value().is_some() => {}
+        Some(t) if t.name() != "_" => help += &format!(" <{}>", t.name()),
+        _ => help += " <value>",
+      }
+      help += "\n";
+
+      if let Some(ref description) = meta.description {
+        help += &format!("      {}\n", description);
+      }
+      if meta.required {
+        help += "      Required\n";
+      }
+      match meta.replaced_by {
+        Some(ref replacement) => help += &format!("      Deprecated, use {}\n", self.flag_name(replacement)),
+        None if meta.deprecated => help += "      Deprecated\n",
+        None => {}
+      }
+      match *defaults.get_path(path) {
+        Value::None => {}
+        _ if meta.secret => {}
+        ref default => help += &format!("      Default: {}\n", default),
+      }
+      let mut vars = vec![env_loader.var_name(application_name, path)];
+      vars.extend(meta.env);
+      help += &format!("      Env: {}\n", vars.join(", "));
+    }
+
+    if let Some(ref flag) = self.help_flag {
+      help += &format!("  {}\n      Prints this help and exits\n", flag);
+    }
+    help
+  }
+
+  /// Returns the flag used for a given config path.
+  fn flag_name(&self, path: &str) -> String {
+    format!("{}{}", self.flag_prefix, path.replace('.', "--").replace('_', "-"))
+  }
+
+  /// Returns the flag without its prefix, or none if the flag doesn't start
+  /// with the prefix. The prefix is matched ignoring case.
+  fn strip_flag_prefix<'a>(&self, arg: &'a str) -> Option<&'a str> {
+    let prefix_len = self.flag_prefix.len();
+    match arg.get(..prefix_len) {
+      Some(prefix) if prefix.eq_ignore_ascii_case(&self.flag_prefix) => Some(&arg[prefix_len..]),
+      _ => None,
+    }
+  }
+
+  fn get_args(&self) -> Vec<(usize, String)> {
+    match self.args {
+      Some(ref args) => args.iter().cloned().enumerate().skip(1).collect(),
+      None => args().enumerate().skip(1).collect(),
     }
   }
 
-  fn get_args(&self) -> Vec<String> {
-    let mut args: Vec<_> = args().into_iter().skip(1).collect();
-    if let Some(ref suffix) = self.suffix {
-      args = args
-        .into_iter()
-        .filter(|arg| arg.to_lowercase().starts_with(suffix))
-        .collect();
+  /// Parses arguments into the path, raw value, and source of each flag and
+  /// positional argument, the arguments which can't be bound to a path, and
+  /// the subcommand if one is given. Arguments after a bare `--` are returned
+  /// as trailing arguments, and a bare `--` is never taken as a flag's value.
+  /// Unknown long flags take the next argument as their value unless it is a
+  /// flag or a subcommand, so a mistyped `--sever--port 8000` is reported
+  /// once. Unknown short flags are reported as the
+  /// flag itself, such as `-x`, and unexpected positional arguments as the
+  /// argument itself.
+  fn parse_flags(&self, args: Vec<(usize, String)>, schema: &Schema) -> ParsedArgs {
+    let takes_value = |path: &str| match schema.path_type(path) {
+      Some(t) => t.flag_value().is_none(),
+      None => false,
+    };
+
+    let mut parsed = ParsedArgs::default();
+    let mut positionals = 0;
+    let mut args = args.into_iter().peekable();
+    while let Some((index, arg)) = args.next() {
+      let mut arg_text = arg.to_owned();
+      let mut next_value = |unknown: bool| {
+        let is_subcommand = |a: &str| schema.subcommands().iter().any(|(name, _)| *name == a);
+        match args.peek() {
+          Some((_, a)) if a == "--" => return None,
+          Some((_, a)) if unknown && (a.starts_with('-') || is_subcommand(a)) => return None,
+          _ => {}
+        }
+        let (_, value) = args.next()?;
+        arg_text = format!("{} {}", arg_text, value);
+        Some(value)
+      };
+
+      if arg == "--" {
+        parsed.trailing_args = args.map(|(_, a)| a).collect();
+        break;
+      }
+
+      if let Some(long) = self.strip_flag_prefix(&arg) {
+        let mut parts = long.splitn(2, '=');
+        let path = flag_path(parts.next().unwrap(), parsed.subcommand.as_deref(), schema);
+        let raw_value = parts.next().map(|v| v.to_owned());
+        if raw_value.is_none() {
+          if let Some((negated_path, negated_value)) = negated_flag(&path, schema) {
+            parsed.flags.push((negated_path, Some(negated_value), Source::CliArg { index, arg }));
+            continue;
+          }
+        }
+        let raw_value = match raw_value {
+          Some(value) => Some(value),
+          None if takes_value(&path) => next_value(false),
+          None if schema.path_type(&path).is_none() => next_value(true),
+          None => None,
+        };
+        parsed.flags.push((path, raw_value, Source::CliArg { index, arg: arg_text }));
+      } else if self.flag_prefix != "--" || arg.starts_with("--") {
+        continue;
+      } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+        let shorts: Vec<char> = shorts.chars().collect();
+        for (i, short) in shorts.iter().enumerate() {
+          let path = match schema.path_for_short(*short) {
+            Some(path) => path.to_owned(),
+            None => {
+              parsed.unknown_args.push((format!("-{}", short), Source::CliArg { index, arg: arg.to_owned() }));
+              continue;
+            }
+          };
+          if !takes_value(&path) {
+            parsed.flags.push((path, None, Source::CliArg { index, arg: arg.to_owned() }));
+            continue;
+          }
+          let rest: String = shorts[i + 1..].iter().collect();
+          let raw_value = if rest.is_empty() { next_value(false) } else { Some(rest) };
+          parsed.flags.push((path, raw_value, Source::CliArg { index, arg: arg_text }));
+          break;
+        }
+      } else if parsed.subcommand.is_none() && schema.subcommands().iter().any(|(name, _)| *name == arg) {
+        parsed.subcommand = Some(arg);
+      } else {
+        let paths: Vec<_> = schema
+          .positional_paths()
+          .into_iter()
+          .filter(|p| schema.subcommand_of(p).is_none() || schema.subcommand_of(p) == parsed.subcommand.as_deref())
+          .collect();
+        let path = match paths.get(positionals) {
+          Some(path) => Some(*path),
+          None => paths.last().cloned().filter(|p| schema.path_type(p).is_some_and(|t| t.item_type().is_some())),
+        };
+        match path {
+          Some(path) => {
+            parsed.positionals.push((path.to_owned(), arg.to_owned(), Source::CliArg { index, arg }));
+            positionals += 1;
+          }
+          None => parsed.unknown_args.push((arg.to_owned(), Source::CliArg { index, arg })),
+        }
+      }
     }
-    args
+    parsed
+  }
+}
+
+/// The flags and positional arguments parsed by `CliLoader`, bound to paths.
+#[derive(Default)]
+struct ParsedArgs {
+  flags: Vec<(String, Option<String>, Source)>,
+  positionals: Vec<(String, String, Source)>,
+  unknown_args: Vec<(String, Source)>,
+  subcommand: Option<String>,
+  trailing_args: Vec<String>,
+}
+
+impl Default for CliLoader {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
 impl Loader for CliLoader {
-  fn collect(&mut self, _: &str, schema: &Schema) -> Result<Value, ConfigError> {
+  fn collect(&mut self, application_name: &str, schema: &Schema) -> Result<Value, ConfigError> {
     let args = self.get_args();
 
-    let flags: Vec<_> = args
-      .into_iter()
-      .filter(|a| a.starts_with("--"))
-      .map(|arg| {
-        let mut arg = arg[2..].split("=");
-
-        let path = arg.next().unwrap().replace("--", ".").replace("-", "_");
-        let raw_value = arg.next().map(|a| a.to_owned());
+    if let Some(ref flag) = self.help_flag {
+      if args.iter().take_while(|(_, a)| a != "--").any(|(_, a)| a == flag) {
+        print!("{}", self.help(application_name, schema));
+        process::exit(0);
+      }
+    }
 
-        (path, raw_value)
-      })
-      .filter(|&(ref p, _)| schema.has_path(&p))
-      .collect();
+    let parsed = self.parse_flags(args, schema);
+    let subcommand = parsed.subcommand;
+    let (flags, unknown_flags): (Vec<_>, Vec<_>) = parsed.flags.into_iter().partition(|(p, _, _)| {
+      let enabled = match schema.subcommand_of(p) {
+        Some(s) => subcommand.as_ref().is_some_and(|subcommand| subcommand == s),
+        None => true,
+      };
+      enabled && schema.path_type(p).is_some()
+    });
 
     let mut values = Value::HashMap(HashMap::new());
+    self.sources.clear();
+    self.subcommand = subcommand;
+    self.trailing_args = parsed.trailing_args;
+    self.unknown_keys = unknown_flags.into_iter().map(|(p, _, s)| (p, s)).collect();
+    self.unknown_keys.extend(parsed.unknown_args);
 
-    for (path, raw_value) in flags {
-      let value = Value::from_raw_with_schema_and_path(raw_value, &path, schema)?;
-      values.set_path(path, value);
+    let flags = flags
+      .into_iter()
+      .map(|(path, raw_value, source)| (Value::from_raw_with_schema_and_path(raw_value, &path, schema), path, source));
+    let positionals = parsed
+      .positionals
+      .into_iter()
+      .map(|(path, raw_value, source)| (positional_value(&raw_value, &path, schema), path, source));
+
+    let mut errors = Vec::new();
+    for (value, path, source) in flags.chain(positionals) {
+      match value {
+        Ok(Value::Vec(items)) => {
+          let mut list = match *values.get_path(&path) {
+            Value::Vec(ref existing) => existing.clone(),
+            _ => Vec::new(),
+          };
+          list.extend(items);
+          values.set_path(&path, Value::Vec(list));
+          self.sources.entry(path).or_insert(source);
+        }
+        Ok(value) => {
+          values.merge_path(&path, value);
+          self.sources.insert(path, source);
+        }
+        Err(e) => errors.extend(e.into_path_errors()?.into_iter().map(|e| e.with_source(source.clone()))),
+      }
     }
 
+    if !errors.is_empty() {
+      return Err(ConfigError::Errors(ConfigErrors(errors)));
+    }
     Ok(values)
   }
+
+  fn name(&self) -> &str {
+    "cli"
+  }
+
+  fn set_args(&mut self, args: Vec<String>) {
+    self.args = Some(args);
+  }
+
+  fn sources(&self) -> HashMap<String, Source> {
+    self.sources.clone()
+  }
+
+  fn unknown_keys(&self) -> Vec<(String, Source)> {
+    self.unknown_keys.clone()
+  }
+
+  fn claims_arg(&self, arg: &str) -> bool {
+    self.flag_prefix != "--" && self.strip_flag_prefix(arg).is_some()
+  }
+
+  fn subcommand(&self) -> Option<String> {
+    self.subcommand.clone()
+  }
+
+  fn trailing_args(&self) -> Vec<String> {
+    self.trailing_args.clone()
+  }
+}
+
+/// Returns the path of a long flag without its prefix, such as `server.port`
+/// for `server--port`, within the subtree of the given subcommand when the
+/// schema has the path there. `-` is replaced with `_` in the segments of
+/// schema paths, but the keys of map entries, such as `X-Request-Id` in
+/// `headers--X-Request-Id`, are kept as they are given.
+fn flag_path(name: &str, subcommand: Option<&str>, schema: &Schema) -> String {
+  let path = name.replace("--", ".").replace('-', "_");
+  let path = match subcommand {
+    Some(subcommand) => subcommand_path(&path, subcommand, schema).unwrap_or(path),
+    None => path,
+  };
+  if let (Some((parent, _)), Some((_, key))) = (path.rsplit_once('.'), name.rsplit_once("--")) {
+    if !schema.has_path(&path) && schema.path_type(parent).is_some_and(|t| t.entry_type().is_some()) {
+      return format!("{}.{}", parent, key);
+    }
+  }
+  path
+}
+
+/// Parses a positional argument for a given path. Arguments of list paths are
+/// parsed whole as a single item, so `a,b.txt` isn't split on `,`.
+fn positional_value(raw_value: &str, path: &str, schema: &Schema) -> Result<Value, ConfigError> {
+  let item_type = match schema.path_type(path).and_then(|t| t.item_type()) {
+    Some(item_type) => item_type,
+    None => return Value::from_raw_with_schema_and_path(Some(raw_value.to_owned()), path, schema),
+  };
+  match item_type.parse(raw_value) {
+    Some(item) => Ok(Value::Vec(vec![item])),
+    None => Err(ConfigError::BadPathType(path.to_owned(), "list")),
+  }
+}
+
+/// Returns the name of a positional path as shown in help, such as `<input>`
+/// for required paths, `[input]` for paths with defaults, and `[input]...`
+/// for lists.
+fn positional_name(path: &str, schema: &Schema) -> String {
+  let required = schema.path_meta(path).is_some_and(|m| m.required);
+  let list = schema.path_type(path).is_some_and(|t| t.item_type().is_some());
+  match (required, list) {
+    (true, false) => format!("<{}>", path),
+    (true, true) => format!("<{}>...", path),
+    (false, false) => format!("[{}]", path),
+    (false, true) => format!("[{}]...", path),
+  }
+}
+
+/// Returns the path of a flag given after a subcommand within the
+/// subcommand's subtree, such as `serve.port` for `--port` after `serve`, if
+/// the schema has it. Negated flags, such as `--no-tls`, become
+/// `no_serve.tls` so they can be read by `negated_flag`.
+fn subcommand_path(path: &str, subcommand: &str, schema: &Schema) -> Option<String> {
+  let scoped_path = format!("{}.{}", subcommand, path);
+  if schema.path_type(&scoped_path).is_some() {
+    return Some(scoped_path);
+  }
+  let negated_path = format!("no_{}.{}", subcommand, path.strip_prefix("no_")?);
+  negated_flag(&negated_path, schema).map(|_| negated_path)
+}
+
+/// Returns the path and raw value of a negated flag, such as `--no-verbose`,
+/// if the path without `no_` can be negated. Paths in the schema starting
+/// with `no_` are never treated as negated.
+fn negated_flag(path: &str, schema: &Schema) -> Option<(String, String)> {
+  if schema.has_path(path) {
+    return None;
+  }
+  let negated_path = path.strip_prefix("no_")?;
+  let value = schema.path_type(negated_path)?.negated_flag_value()?;
+  Some((negated_path.to_owned(), value.to_raw()?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Vec<(usize, String)> {
+    args.iter().enumerate().map(|(i, a)| (i + 1, a.to_string())).collect()
+  }
+
+  #[test]
+  fn can_parse_space_separated_values_and_short_flags() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .short('p')
+      .path::<bool>("verbose", false)
+      .short('v')
+      .path::<bool>("quiet", false)
+      .short('q')
+      .path::<String>("log_level", "info".into())
+      .build();
+
+    let parsed = CliLoader::new().parse_flags(args(&["--log-level", "debug", "-vq", "-p", "8000", "-x"]), &schema);
+
+    let flags: Vec<_> = parsed.flags.into_iter().map(|(p, v, _)| (p, v)).collect();
+    assert_eq!(
+      flags,
+      vec![
+        ("log_level".into(), Some("debug".into())),
+        ("verbose".into(), None),
+        ("quiet".into(), None),
+        ("server.port".into(), Some("8000".into())),
+      ]
+    );
+    assert_eq!(parsed.unknown_args[0].0, "-x");
+  }
+
+  #[test]
+  fn can_report_unknown_flag_with_space_separated_value_once() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let mut loader = CliLoader::from_args(vec!["app", "--sever--port", "8000", "--other", "--verbose"]);
+
+    loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(
+      loader.unknown_keys(),
+      vec![
+        (
+          "sever.port".into(),
+          Source::CliArg {
+            index: 1,
+            arg: "--sever--port 8000".into()
+          }
+        ),
+        (
+          "other".into(),
+          Source::CliArg {
+            index: 3,
+            arg: "--other".into()
+          }
+        ),
+        (
+          "verbose".into(),
+          Source::CliArg {
+            index: 4,
+            arg: "--verbose".into()
+          }
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn can_parse_negated_and_explicit_bool_flags() {
+    let schema = Schema::new()
+      .path::<bool>("verbose", true)
+      .path::<bool>("color", true)
+      .path::<bool>("no_cache", false)
+      .build();
+
+    let flags = CliLoader::new().parse_flags(args(&["--no-verbose", "--color=off", "--no-cache"]), &schema);
+
+    let flags: Vec<_> = flags.flags.into_iter().map(|(p, v, _)| (p, v)).collect();
+    assert_eq!(
+      flags,
+      vec![
+        ("verbose".into(), Some("false".into())),
+        ("color".into(), Some("off".into())),
+        ("no_cache".into(), None),
+      ]
+    );
+  }
+
+  #[test]
+  fn can_collect_lists_from_repeated_and_delimited_flags() {
+    let schema = Schema::new().path::<Vec<u16>>("ports", vec![]).build();
+    let mut loader = CliLoader::from_args(vec!["app", "--ports=80,443", "--ports", "8080"]);
+
+    let values = loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(
+      *values.get_path("ports"),
+      Value::Vec(vec![Value::U16(80), Value::U16(443), Value::U16(8080)])
+    );
+    assert_eq!(
+      loader.sources()["ports"],
+      Source::CliArg {
+        index: 1,
+        arg: "--ports=80,443".into()
+      }
+    );
+  }
+
+  #[test]
+  fn can_collect_maps_from_entries_and_entry_flags() {
+    let schema = Schema::new()
+      .path::<HashMap<String, String>>("labels", HashMap::new())
+      .build();
+
+    let values = CliLoader::from_args(vec!["app", "--labels=env=prod", "--labels--team=core", "--labels", "tier=1"])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(*values.get_path("labels.env"), Value::String("prod".into()));
+    assert_eq!(*values.get_path("labels.team"), Value::String("core".into()));
+    assert_eq!(*values.get_path("labels.tier"), Value::String("1".into()));
+  }
+
+  #[test]
+  fn can_keep_map_entry_keys_as_given() {
+    let schema = Schema::new()
+      .path::<HashMap<String, String>>("http_headers", HashMap::new())
+      .build();
+
+    let values = CliLoader::from_args(vec!["app", "--http-headers--X-Request-Id=abc", "--http-headers=X-Other=1"])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(*values.get_path("http_headers.X-Request-Id"), Value::String("abc".into()));
+    assert_eq!(*values.get_path("http_headers.X-Other"), Value::String("1".into()));
+  }
+
+  #[test]
+  fn can_collect_prefixed_flags_only() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<bool>("verbose", true)
+      .build();
+    let mut loader = CliLoader::with_prefix("myapp").args(vec![
+      "app",
+      "--MyApp-server--port",
+      "8000",
+      "--myapp-no-verbose",
+      "--server--port=1",
+      "--other-flag",
+    ]);
+
+    let values = loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(*values.get_path("server.port"), Value::U32(8000));
+    assert_eq!(*values.get_path("verbose"), Value::Bool(false));
+    assert!(loader.unknown_keys().is_empty());
+  }
+
+  #[test]
+  #[allow(deprecated)]
+  fn can_collect_flags_with_deprecated_suffix() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+
+    let values = CliLoader::with_suffix("--myapp")
+      .args(vec!["app", "--myapp--server--port=8000"])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(*values.get_path("server.port"), Value::U32(8000));
+
+    let values = CliLoader::with_suffix("myapp")
+      .args(vec!["app", "myapp--server--port=8000", "--server--port=1"])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(*values.get_path("server.port"), Value::U32(8000));
+  }
+
+  #[test]
+  fn can_report_space_separated_value_in_source() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).short('p').build();
+
+    let flags = CliLoader::new().parse_flags(args(&["-vp8000", "--server--port", "9000"]), &schema).flags;
+
+    assert_eq!(flags[0].1, Some("8000".into()));
+    assert_eq!(
+      flags[1].2,
+      Source::CliArg {
+        index: 2,
+        arg: "--server--port 9000".into()
+      }
+    );
+  }
+
+  #[test]
+  fn can_generate_help_from_schema() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .describe("The port to listen on")
+      .path::<bool>("verbose", false)
+      .short('v')
+      .required_path::<String>("db.password")
+      .secret()
+      .env("DATABASE_PASSWORD")
+      .build();
+
+    let help = CliLoader::new().help("my-app", &schema);
+
+    assert_eq!(
+      help,
+      "Usage: my-app [OPTIONS]\n\
+       \n\
+       Options:\n  \
+       --db--password <String>\n      \
+       Required\n      \
+       Env: MY_APP_DB__PASSWORD, DATABASE_PASSWORD\n  \
+       --server--port <u32>\n      \
+       The port to listen on\n      \
+       Default: 3000\n      \
+       Env: MY_APP_SERVER__PORT\n  \
+       -v, --[no-]verbose\n      \
+       Default: false\n      \
+       Env: MY_APP_VERBOSE\n  \
+       --help\n      \
+       Prints this help and exits\n"
+    );
+  }
+
+  #[test]
+  fn can_generate_help_with_prefixed_flags() {
+    let schema = Schema::new().path::<bool>("verbose", false).build();
+
+    let help = CliLoader::with_prefix("myapp").help("my-app", &schema);
+
+    assert!(help.contains("  --myapp-[no-]verbose\n"));
+    assert!(help.contains("  --myapp-help\n"));
+  }
+
+  #[test]
+  fn can_collect_positional_arguments() {
+    let schema = Schema::new()
+      .required_path::<String>("input")
+      .positional()
+      .path::<Vec<String>>("extra", vec![])
+      .positional()
+      .path::<bool>("verbose", false)
+      .build();
+    let mut loader = CliLoader::from_args(vec!["app", "in.txt", "--verbose", "a", "b"]);
+
+    let values = loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(*values.get_path("input"), Value::String("in.txt".into()));
+    assert_eq!(
+      *values.get_path("extra"),
+      Value::Vec(vec![Value::String("a".into()), Value::String("b".into())])
+    );
+    assert_eq!(
+      loader.sources()["input"],
+      Source::CliArg {
+        index: 1,
+        arg: "in.txt".into()
+      }
+    );
+  }
+
+  #[test]
+  fn can_collect_positional_list_items_whole() {
+    let schema = Schema::new()
+      .path::<Vec<String>>("files", vec![])
+      .positional()
+      .build();
+
+    let values = CliLoader::from_args(vec!["app", "a,b.txt", "c.txt"])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(
+      *values.get_path("files"),
+      Value::Vec(vec![Value::String("a,b.txt".into()), Value::String("c.txt".into())])
+    );
+  }
+
+  #[test]
+  fn can_report_unexpected_positional_arguments() {
+    let schema = Schema::new().path::<bool>("verbose", false).build();
+    let mut loader = CliLoader::from_args(vec!["app", "verbose"]);
+
+    let values = loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(*values.get_path("verbose"), Value::None);
+    assert_eq!(loader.unknown_keys()[0].0, "verbose");
+  }
+
+  #[test]
+  fn can_collect_subcommand_and_its_subtree() {
+    let schema = Schema::new()
+      .subcommand("serve", "Starts the server")
+      .subcommand("check", "Checks the config")
+      .path::<u32>("serve.port", 3000)
+      .path::<bool>("serve.tls", true)
+      .path::<String>("serve.root", ".".into())
+      .positional()
+      .path::<bool>("check.strict", false)
+      .build();
+    let mut loader = CliLoader::from_args(vec!["app", "serve", "--port", "8000", "--no-tls", "www", "--check--strict"]);
+
+    let values = loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(loader.subcommand(), Some("serve".into()));
+    assert_eq!(*values.get_path("serve.port"), Value::U32(8000));
+    assert_eq!(*values.get_path("serve.tls"), Value::Bool(false));
+    assert_eq!(*values.get_path("serve.root"), Value::String("www".into()));
+    assert_eq!(*values.get_path("check.strict"), Value::None);
+    assert_eq!(loader.unknown_keys()[0].0, "check.strict");
+  }
+
+  #[test]
+  fn can_generate_help_with_commands_and_arguments() {
+    let schema = Schema::new()
+      .subcommand("serve", "Starts the server")
+      .required_path::<String>("input")
+      .positional()
+      .describe("The file to read")
+      .path::<String>("serve.root", ".".into())
+      .positional()
+      .build();
+
+    let help = CliLoader::new().without_help().help("my-app", &schema);
+
+    assert!(help.starts_with(
+      "Usage: my-app [OPTIONS] [COMMAND] <input>\n\n\
+       Commands:\n  \
+       serve\n      \
+       Starts the server\n\n\
+       Arguments:\n  \
+       <input>\n      \
+       The file to read\n  \
+       serve [serve.root]\n\n\
+       Options:\n"
+    ));
+  }
+
+  #[test]
+  fn can_stop_parsing_at_double_dash() {
+    let schema = Schema::new()
+      .path::<bool>("verbose", false)
+      .path::<String>("input", "-".into())
+      .positional()
+      .build();
+    let mut loader = CliLoader::from_args(vec!["app", "in.txt", "--", "--verbose", "--help", "out.txt"]);
+
+    let values = loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(*values.get_path("input"), Value::String("in.txt".into()));
+    assert_eq!(*values.get_path("verbose"), Value::None);
+    assert_eq!(loader.trailing_args(), vec!["--verbose", "--help", "out.txt"]);
+    assert!(loader.unknown_keys().is_empty());
+  }
+
+  #[test]
+  fn cannot_take_double_dash_as_flag_value() {
+    let schema = Schema::new().path::<String>("name", "app".into()).build();
+
+    let parsed = CliLoader::new().parse_flags(args(&["--name", "--", "ls"]), &schema);
+
+    let flags: Vec<_> = parsed.flags.into_iter().map(|(p, v, _)| (p, v)).collect();
+    assert_eq!(flags, vec![("name".into(), None)]);
+    assert_eq!(parsed.trailing_args, vec!["ls"]);
+
+    let mut loader = CliLoader::from_args(vec!["app", "--name", "--", "ls"]);
+    assert!(loader.collect("my_app", &schema).is_err());
+    assert_eq!(loader.trailing_args(), vec!["ls"]);
+  }
 }
@@ -1,23 +1,383 @@
+use std::env::var_os;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use failure::Error;
 use config::ConfigError;
+use errors::{ConfigErrors, PathError, PathErrorKind};
+use format::Format;
+use source::Source;
 use value::Value;
 use loaders::loader::Loader;
 use schema::Schema;
 
-pub struct DiskLoader {}
+/// Loads config values from configuration files. Files named after the
+/// application, `<application_name>.<extension>`, are searched for in the
+/// following locations:
+///
+/// - `/etc/<application_name>/`
+/// - `$XDG_CONFIG_HOME/<application_name>/` (or `~/.config/<application_name>/`)
+/// - the current working directory
+///
+/// Within each location files are searched for in the order given by
+/// `Format::all`, using every extension of each enabled format. Every file
+/// found is loaded. Values from files found later take precedence over values
+/// from files found earlier. Tables in the file are mapped to config paths, so
+/// `port` within a `[server]` table becomes `server.port`.
+pub struct DiskLoader {
+  path: Option<PathBuf>,
+  format: Option<Format>,
+  sources: HashMap<String, Source>,
+  unknown_keys: Vec<(String, Source)>,
+}
 
 impl DiskLoader {
+  /// Create a new disk loader which searches for configuration files named
+  /// after the application.
   pub fn new() -> Self {
-    Self {}
+    Self {
+      path: None,
+      format: None,
+      sources: HashMap::new(),
+      unknown_keys: Vec::new(),
+    }
+  }
+
+  /// Create a new disk loader which loads a single given file instead of
+  /// searching for files. Unlike searched files, the file must exist.
+  pub fn with_path<P: AsRef<Path>>(path: P) -> Self {
+    Self {
+      path: Some(path.as_ref().to_owned()),
+      format: None,
+      sources: HashMap::new(),
+      unknown_keys: Vec::new(),
+    }
+  }
+
+  /// Sets the format used to parse configuration files instead of picking it
+  /// from the file extension. When searching, only files with an extension of
+  /// the given format are loaded.
+  pub fn format(mut self, format: Format) -> Self {
+    self.format = Some(format);
+    self
+  }
+
+  fn get_formats(&self) -> Vec<Format> {
+    match self.format {
+      Some(format) => vec![format],
+      None => Format::all().into_iter().filter(|f| f.is_enabled()).collect(),
+    }
+  }
+
+  fn get_search_paths(&self, application_name: &str) -> Vec<PathBuf> {
+    let formats = self.get_formats();
+    let mut dirs = vec![Path::new("/etc").join(application_name)];
+
+    if let Some(config_home) = var_os("XDG_CONFIG_HOME") {
+      dirs.push(PathBuf::from(config_home).join(application_name));
+    } else if let Some(home) = var_os("HOME") {
+      dirs.push(PathBuf::from(home).join(".config").join(application_name));
+    }
+    dirs.push(PathBuf::new());
+
+    let mut paths = Vec::new();
+    for dir in dirs {
+      for format in formats.iter() {
+        for extension in format.extensions() {
+          paths.push(dir.join(format!("{}.{}", application_name, extension)));
+        }
+      }
+    }
+    paths
+  }
+
+  fn read_file(path: &Path) -> Result<Option<String>, DiskLoaderError> {
+    let mut file = match File::open(path) {
+      Ok(f) => f,
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(DiskLoaderError::Read(path.to_owned(), e)),
+    };
+    let mut contents = String::new();
+    file
+      .read_to_string(&mut contents)
+      .map_err(|e| DiskLoaderError::Read(path.to_owned(), e))?;
+    Ok(Some(contents))
+  }
+
+  fn parse_file(&self, path: &Path, contents: &str) -> Result<Value, DiskLoaderError> {
+    let format = self
+      .format
+      .or_else(|| Format::from_path(path))
+      .ok_or_else(|| DiskLoaderError::UnknownFormat(path.to_owned()))?;
+    format
+      .parse(contents)
+      .map_err(|e| DiskLoaderError::Parse(path.to_owned(), e))
+  }
+}
+
+impl Default for DiskLoader {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
 impl Loader for DiskLoader {
-  fn collect(&mut self, _application_name: &str, _schema: &Schema) -> Result<Value, ConfigError> {
-    unimplemented!()
+  fn collect(&mut self, application_name: &str, schema: &Schema) -> Result<Value, ConfigError> {
+    let paths = match self.path {
+      Some(ref path) => vec![path.to_owned()],
+      None => self.get_search_paths(application_name),
+    };
+
+    let mut values = Value::HashMap(HashMap::new());
+    let mut errors = Vec::new();
+    self.sources.clear();
+    self.unknown_keys.clear();
+
+    for path in paths {
+      let contents = match Self::read_file(&path) {
+        Ok(Some(c)) => c,
+        Ok(None) if self.path.is_none() => continue,
+        Ok(None) => {
+          errors.push(file_error(DiskLoaderError::NotFound(path)));
+          continue;
+        }
+        Err(e) => {
+          errors.push(file_error(e));
+          continue;
+        }
+      };
+      let value = match self.parse_file(&path, &contents) {
+        Ok(value) => value,
+        Err(e) => {
+          errors.push(file_error(e));
+          continue;
+        }
+      };
+      for leaf_path in value.leaf_paths() {
+        if !schema.covers_path(&leaf_path) {
+          let source = Source::File {
+            path: path.to_owned(),
+            position: locate_path(&contents, &leaf_path),
+          };
+          self.unknown_keys.push((leaf_path, source));
+        }
+      }
+      let value = match Value::from_value_with_schema_and_path(value, "", schema) {
+        Ok(value) => value,
+        Err(e) => {
+          errors.extend(e.into_path_errors()?.into_iter().map(|e| {
+            let source = Source::File {
+              path: path.to_owned(),
+              position: locate_path(&contents, &e.path),
+            };
+            e.with_source(source)
+          }));
+          continue;
+        }
+      };
+      for leaf_path in value.leaf_paths() {
+        let source = Source::File {
+          path: path.to_owned(),
+          position: locate_path(&contents, &leaf_path),
+        };
+        self.sources.insert(leaf_path, source);
+      }
+      values.merge(value);
+    }
+
+    if !errors.is_empty() {
+      return Err(ConfigError::Errors(ConfigErrors(errors)));
+    }
+    Ok(values)
+  }
+
+  fn name(&self) -> &str {
+    "disk"
+  }
+
+  fn sources(&self) -> HashMap<String, Source> {
+    self.sources.clone()
+  }
+
+  fn unknown_keys(&self) -> Vec<(String, Source)> {
+    self.unknown_keys.clone()
+  }
+}
+
+/// Finds the line and column of the key of a given config path within the
+/// source of a config file. Each key of the path is searched for in turn,
+/// starting after the key before it. This works for all supported formats,
+/// but as the source isn't parsed it is a best guess.
+/// Converts an error reading or parsing a config file into a path error with
+/// the file as its source, so it can be gathered with the errors of other
+/// files and loaders.
+fn file_error(error: DiskLoaderError) -> PathError {
+  let (path, message) = match error {
+    DiskLoaderError::NotFound(path) => (path, "config file does not exist".to_owned()),
+    DiskLoaderError::UnknownFormat(path) => (path, "cannot determine format of config file".to_owned()),
+    DiskLoaderError::Read(path, e) => (path, format!("cannot read config file: {}", e)),
+    DiskLoaderError::Parse(path, e) => (path, format!("cannot parse config file: {}", e)),
+    e => return PathError::new("", PathErrorKind::Loader(e.to_string())),
+  };
+  PathError::new("", PathErrorKind::Loader(message)).with_source(Source::File { path, position: None })
+}
+
+fn locate_path(contents: &str, path: &str) -> Option<(usize, usize)> {
+  let mut offset = 0;
+  let mut key_offset = 0;
+  for key in path.split('.') {
+    key_offset = find_key(&contents[offset..], key)? + offset;
+    offset = key_offset + key.len();
   }
+
+  let before = &contents[..key_offset];
+  let line = before.matches('\n').count() + 1;
+  let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+  Some((line, column))
+}
+
+fn find_key(contents: &str, key: &str) -> Option<usize> {
+  let is_key_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+  let mut offset = 0;
+  while let Some(index) = contents[offset..].find(key) {
+    let start = offset + index;
+    let end = start + key.len();
+    let before_ok = !contents[..start].chars().next_back().is_some_and(is_key_char);
+    let after_ok = contents[end..]
+      .trim_start_matches(&['"', '\'', ' ', '\t'][..])
+      .starts_with(&['=', ':', ']', '.'][..]);
+    if before_ok && after_ok {
+      return Some(start);
+    }
+    offset = end;
+  }
+  None
 }
 
 #[derive(Debug, Fail)]
 pub enum DiskLoaderError {
+  #[fail(display = "Config file {:?} does not exist", _0)] NotFound(PathBuf),
+  #[fail(display = "Cannot determine format of config file {:?}", _0)] UnknownFormat(PathBuf),
+  #[fail(display = "Cannot read config file {:?}: {}", _0, _1)] Read(PathBuf, #[fail(cause)] io::Error),
+  #[fail(display = "Cannot parse config file {:?}: {}", _0, _1)] Parse(PathBuf, #[fail(cause)] Error),
   #[fail(display = "Unknown error")] Unknown,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  #[cfg(feature = "toml")]
+  use std::env::temp_dir;
+  #[cfg(feature = "toml")]
+  use std::fs::{remove_file, File};
+  #[cfg(feature = "toml")]
+  use std::io::Write;
+
+  #[test]
+  fn can_search_for_files_named_after_the_application() {
+    let paths = DiskLoader::new()
+      .format(Format::Toml)
+      .get_search_paths("my_app");
+
+    assert_eq!(paths[0], PathBuf::from("/etc/my_app/my_app.toml"));
+    assert_eq!(*paths.last().unwrap(), PathBuf::from("my_app.toml"));
+  }
+
+  #[test]
+  fn can_search_for_every_extension_of_a_format() {
+    let paths = DiskLoader::new()
+      .format(Format::Yaml)
+      .get_search_paths("my_app");
+
+    assert_eq!(paths[0], PathBuf::from("/etc/my_app/my_app.yaml"));
+    assert_eq!(paths[1], PathBuf::from("/etc/my_app/my_app.yml"));
+  }
+
+  #[cfg(feature = "toml")]
+  #[test]
+  fn can_collect_values_from_toml_file() {
+    let path = temp_dir().join("unison_disk_loader_test.toml");
+    File::create(&path)
+      .unwrap()
+      .write_all(b"[server]\nport = 8000\nunknown = true\n")
+      .unwrap();
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+
+    let values = DiskLoader::with_path(&path).collect("my_app", &schema);
+    remove_file(&path).unwrap();
+    let values = values.unwrap();
+
+    assert_eq!(*values.get_path("server.port"), Value::U32(8000));
+    assert_eq!(*values.get_path("server.unknown"), Value::None);
+  }
+
+  #[cfg(feature = "toml")]
+  #[test]
+  fn can_report_file_sources() {
+    let path = temp_dir().join("unison_disk_loader_sources_test.toml");
+    File::create(&path)
+      .unwrap()
+      .write_all(b"[server]\nport = 8000\n")
+      .unwrap();
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let mut loader = DiskLoader::with_path(&path);
+
+    let result = loader.collect("my_app", &schema);
+    remove_file(&path).unwrap();
+    result.unwrap();
+
+    assert_eq!(
+      loader.sources()["server.port"],
+      Source::File {
+        path: path.to_owned(),
+        position: Some((2, 1)),
+      }
+    );
+  }
+
+  #[cfg(all(feature = "toml", feature = "json", feature = "yaml", feature = "ini"))]
+  #[test]
+  fn can_collect_same_values_from_all_formats() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let sources = vec![
+      ("toml", "[server]\nport = 8000\n"),
+      ("json", "{\"server\": {\"port\": 8000}}"),
+      ("yaml", "server:\n  port: 8000\n"),
+      ("ini", "[server]\nport = 8000\n"),
+    ];
+
+    for (extension, source) in sources {
+      let path = temp_dir().join(format!("unison_disk_loader_formats_test.{}", extension));
+      File::create(&path).unwrap().write_all(source.as_bytes()).unwrap();
+
+      let values = DiskLoader::with_path(&path).collect("my_app", &schema);
+      remove_file(&path).unwrap();
+
+      assert_eq!(*values.unwrap().get_path("server.port"), Value::U32(8000));
+    }
+  }
+
+  #[test]
+  fn can_locate_config_paths_in_source() {
+    let contents = "# port = 1\n[server]\nhost = \"port\"\n  port = 8000\n";
+
+    assert_eq!(locate_path(contents, "server.port"), Some((4, 3)));
+    assert_eq!(locate_path(contents, "server.missing"), None);
+  }
+
+  #[test]
+  fn cannot_collect_values_from_missing_file() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+
+    let result = DiskLoader::with_path("/does/not/exist.toml").collect("my_app", &schema);
+
+    match result {
+      Err(ConfigError::Errors(errors)) => assert_eq!(
+        errors.to_string(),
+        "1 config error:\n  config file does not exist (from /does/not/exist.toml)"
+      ),
+      r => panic!("expected missing file error, got {:?}", r),
+    }
+  }
+}
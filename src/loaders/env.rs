@@ -1,19 +1,175 @@
+use std::env::vars_os;
+use std::collections::HashMap;
 use config::ConfigError;
+use errors::ConfigErrors;
+use source::Source;
 use value::Value;
 use loaders::loader::Loader;
 use schema::Schema;
 
-pub struct EnvLoader {}
+/// Loads config values from environment variables. Variables are mapped to
+/// config paths by removing the prefix, lowercasing the remaining name, and
+/// replacing `__` with `.`. By default the prefix is derived from the
+/// application name by uppercasing it and replacing any character that isn't
+/// alphanumeric with `_`.
+/// For example, with an application name of `myapp`, `MYAPP_SERVER__PORT=8000`
+/// becomes `server.port` with a value of `8000` (parsed into the expected type
+/// provided by the schema). Lists are given as comma separated values, such
+/// as `MYAPP_HOSTS=a,b`, and maps as comma separated `key=value` entries or a
+/// variable per entry, such as `MYAPP_LABELS=env=prod` and
+/// `MYAPP_LABELS__TEAM=core`. Paths with an environment variable set in the
+/// schema are also read from that variable, when the prefixed variable isn't
+/// set.
+pub struct EnvLoader {
+  prefix: Option<String>,
+  vars: Option<Vec<(String, String)>>,
+  sources: HashMap<String, Source>,
+  unknown_keys: Vec<(String, Source)>,
+}
 
 impl EnvLoader {
+  /// Create a new env loader which derives its prefix from the application
+  /// name.
   pub fn new() -> Self {
-    Self {}
+    Self {
+      prefix: None,
+      vars: None,
+      sources: HashMap::new(),
+      unknown_keys: Vec::new(),
+    }
+  }
+
+  /// Create a new env loader with a given prefix. The prefix will be expected
+  /// at the start of all variable names and is followed by `_`. For example
+  /// a prefix of `OTHER` will match `OTHER_SERVER__PORT`.
+  pub fn with_prefix(prefix: &str) -> Self {
+    Self {
+      prefix: Some(prefix.to_uppercase()),
+      vars: None,
+      sources: HashMap::new(),
+      unknown_keys: Vec::new(),
+    }
+  }
+
+  /// Create a new env loader which derives its prefix from the application
+  /// name and reads the given variables instead of the process environment.
+  /// See `EnvLoader::vars`.
+  pub fn from_vars<I, K, V>(vars: I) -> Self
+  where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+  {
+    Self::new().vars(vars)
+  }
+
+  /// Sets the variables the loader reads instead of the process environment.
+  pub fn vars<I, K, V>(mut self, vars: I) -> Self
+  where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+  {
+    self.set_vars(vars.into_iter().map(|(k, v)| (k.into(), v.into())).collect());
+    self
+  }
+
+  fn get_prefix(&self, application_name: &str) -> String {
+    let prefix = match self.prefix {
+      Some(ref prefix) => prefix.to_owned(),
+      None => application_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .to_uppercase(),
+    };
+    prefix + "_"
+  }
+
+  /// Returns the name of the variable the loader reads a given config path
+  /// from.
+  pub fn var_name(&self, application_name: &str, path: &str) -> String {
+    self.get_prefix(application_name) + &path.to_uppercase().replace('.', "__")
+  }
+
+  fn get_vars(&self) -> Vec<(String, String)> {
+    if let Some(ref vars) = self.vars {
+      return vars.clone();
+    }
+    vars_os()
+      .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+      .collect()
+  }
+}
+
+impl Default for EnvLoader {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
 impl Loader for EnvLoader {
-  fn collect(&mut self, _application_name: &str, _schema: &Schema) -> Result<Value, ConfigError> {
-    unimplemented!()
+  fn collect(&mut self, application_name: &str, schema: &Schema) -> Result<Value, ConfigError> {
+    let prefix = self.get_prefix(application_name);
+
+    let all_vars: HashMap<String, String> = self.get_vars().into_iter().collect();
+    let (mut vars, unknown_vars): (Vec<_>, Vec<_>) = all_vars
+      .iter()
+      .filter(|(key, _)| key.starts_with(&prefix))
+      .map(|(key, raw_value)| {
+        let path = key[prefix.len()..].to_lowercase().replace("__", ".");
+        (path, raw_value.to_owned(), Source::EnvVar { name: key.to_owned() })
+      })
+      .partition(|(p, _, _)| schema.path_type(p).is_some());
+    self.unknown_keys = unknown_vars.into_iter().map(|(p, _, s)| (p, s)).collect();
+
+    for path in schema.path_names() {
+      let name = match schema.path_meta(path).and_then(|m| m.env.as_ref()) {
+        Some(name) => name,
+        None => continue,
+      };
+      if vars.iter().any(|(p, _, _)| p == path) {
+        continue;
+      }
+      if let Some(raw_value) = all_vars.get(name) {
+        vars.push((path.to_owned(), raw_value.to_owned(), Source::EnvVar { name: name.to_owned() }));
+      }
+    }
+
+    let mut values = Value::HashMap(HashMap::new());
+    self.sources.clear();
+
+    let mut errors = Vec::new();
+    for (path, raw_value, source) in vars {
+      match Value::from_raw_with_schema_and_path(Some(raw_value), &path, schema) {
+        Ok(value) => {
+          values.merge_path(&path, value);
+          self.sources.insert(path, source);
+        }
+        Err(e) => errors.extend(e.into_path_errors()?.into_iter().map(|e| e.with_source(source.clone()))),
+      }
+    }
+
+    if !errors.is_empty() {
+      return Err(ConfigError::Errors(ConfigErrors(errors)));
+    }
+    Ok(values)
+  }
+
+  fn name(&self) -> &str {
+    "env"
+  }
+
+  fn set_vars(&mut self, vars: Vec<(String, String)>) {
+    self.vars = Some(vars);
+  }
+
+  fn sources(&self) -> HashMap<String, Source> {
+    self.sources.clone()
+  }
+
+  fn unknown_keys(&self) -> Vec<(String, Source)> {
+    self.unknown_keys.clone()
   }
 }
 
@@ -21,3 +177,127 @@ impl Loader for EnvLoader {
 pub enum EnvLoaderError {
   #[fail(display = "Unknown error")] Unknown,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn can_derive_prefix_from_application_name() {
+    let loader = EnvLoader::new();
+
+    assert_eq!(loader.get_prefix("my-app"), "MY_APP_");
+  }
+
+  #[test]
+  fn can_get_var_name_of_path() {
+    let loader = EnvLoader::new();
+
+    assert_eq!(loader.var_name("my-app", "server.port"), "MY_APP_SERVER__PORT");
+  }
+
+  #[test]
+  fn can_collect_values_from_env_vars() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let vars = vec![("MY_APP_SERVER__PORT", "8000"), ("MY_APP_SERVER__UNKNOWN", "8000")];
+
+    let values = EnvLoader::from_vars(vars).collect("my_app", &schema).unwrap();
+
+    assert_eq!(*values.get_path("server.port"), Value::U32(8000));
+    assert_eq!(*values.get_path("server.unknown"), Value::None);
+  }
+
+  #[test]
+  fn can_report_env_var_sources() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let mut loader = EnvLoader::from_vars(vec![("MY_APP_SERVER__PORT", "8000")]);
+
+    loader.collect("my_app", &schema).unwrap();
+
+    assert_eq!(
+      loader.sources()["server.port"],
+      Source::EnvVar {
+        name: "MY_APP_SERVER__PORT".into()
+      }
+    );
+  }
+
+  #[test]
+  fn can_collect_values_from_env_var_set_in_schema() {
+    let schema = Schema::new()
+      .required_path::<String>("db.password")
+      .env("DATABASE_PASSWORD")
+      .build();
+
+    let values = EnvLoader::from_vars(vec![("DATABASE_PASSWORD", "hunter2")])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(*values.get_path("db.password"), Value::String("hunter2".into()));
+  }
+
+  #[test]
+  fn can_report_every_bad_env_var() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<u8>("server.workers", 4)
+      .build();
+
+    let vars = vec![("MY_APP_SERVER__PORT", "high"), ("MY_APP_SERVER__WORKERS", "many")];
+
+    let result = EnvLoader::from_vars(vars).collect("my_app", &schema);
+
+    let mut errors = match result {
+      Err(ConfigError::Errors(errors)) => errors.0,
+      r => panic!("expected errors, got {:?}", r),
+    };
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(
+      errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+      vec![
+        "server.port: cannot parse value into type u32 (from environment variable `MY_APP_SERVER__PORT`)",
+        "server.workers: cannot parse value into type u8 (from environment variable `MY_APP_SERVER__WORKERS`)",
+      ]
+    );
+  }
+
+  #[test]
+  fn can_collect_lists_from_delimited_env_vars() {
+    let schema = Schema::new().path::<Vec<String>>("hosts", vec![]).build();
+
+    let values = EnvLoader::from_vars(vec![("MY_APP_HOSTS", "a,b")])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(
+      *values.get_path("hosts"),
+      Value::Vec(vec![Value::String("a".into()), Value::String("b".into())])
+    );
+  }
+
+  #[test]
+  fn can_collect_maps_from_env_vars() {
+    let schema = Schema::new()
+      .path::<HashMap<String, String>>("labels", HashMap::new())
+      .build();
+
+    let vars = vec![("MY_APP_LABELS", "env=prod"), ("MY_APP_LABELS__TEAM", "core")];
+
+    let values = EnvLoader::from_vars(vars).collect("my_app", &schema).unwrap();
+
+    assert_eq!(*values.get_path("labels.env"), Value::String("prod".into()));
+    assert_eq!(*values.get_path("labels.team"), Value::String("core".into()));
+  }
+
+  #[test]
+  fn can_collect_values_with_custom_prefix() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+
+    let values = EnvLoader::with_prefix("other")
+      .vars(vec![("OTHER_SERVER__PORT", "8000")])
+      .collect("my_app", &schema)
+      .unwrap();
+
+    assert_eq!(*values.get_path("server.port"), Value::U32(8000));
+  }
+}
@@ -1,7 +1,70 @@
+use std::collections::HashMap;
 use schema::Schema;
+use source::Source;
 use value::Value;
 use config::ConfigError;
 
+/// Loader is implemented by every configuration source. Loaders are executed
+/// by `Config::init`, each producing a value tree of the config values it
+/// found.
+///
+/// Only `collect` must be implemented. The other methods report what the
+/// last call to `collect` found besides values, or set the input of loaders
+/// which read command line arguments or environment variables. Their default
+/// implementations report nothing and ignore the input, for loaders which
+/// don't track or read it.
 pub trait Loader {
+  /// Collects config values from the loader's source. Any values without a
+  /// matching path in the schema should be left out, and reported by
+  /// `unknown_keys` instead.
   fn collect(&mut self, application_name: &str, schema: &Schema) -> Result<Value, ConfigError>;
+
+  /// Returns the name of the loader. Names are used to identify loaders when
+  /// configuring a `ConfigBuilder`.
+  fn name(&self) -> &str {
+    "custom"
+  }
+
+  /// Sets the command line arguments the loader reads, starting with the
+  /// program name. Used by `ConfigBuilder::args`.
+  fn set_args(&mut self, _args: Vec<String>) {}
+
+  /// Sets the environment variables the loader reads. Used by
+  /// `ConfigBuilder::vars`.
+  fn set_vars(&mut self, _vars: Vec<(String, String)>) {}
+
+  /// Returns the sources of the values found by the last call to `collect`,
+  /// keyed by config path. Values without a source are reported with
+  /// `Source::Unknown`.
+  fn sources(&self) -> HashMap<String, Source> {
+    HashMap::new()
+  }
+
+  /// Returns the paths found by the last call to `collect` which aren't in
+  /// the schema, along with their sources. `Config::init` handles them as
+  /// set by `ConfigBuilder::unknown_keys`.
+  fn unknown_keys(&self) -> Vec<(String, Source)> {
+    Vec::new()
+  }
+
+  /// Returns whether the loader reads the given command line argument, such
+  /// as a flag with the loader's own prefix. `Config::init` doesn't report
+  /// arguments claimed by one loader as unknown keys of another.
+  fn claims_arg(&self, _arg: &str) -> bool {
+    false
+  }
+
+  /// Returns the subcommand found by the last call to `collect`, if any.
+  /// `Config::init` keeps the subcommand of the first loader which found
+  /// one.
+  fn subcommand(&self) -> Option<String> {
+    None
+  }
+
+  /// Returns the arguments found by the last call to `collect` which were
+  /// left for another program, such as those after `--` on the command line.
+  /// `Config::init` keeps the arguments of the first loader which found any.
+  fn trailing_args(&self) -> Vec<String> {
+    Vec::new()
+  }
 }
@@ -1,19 +1,65 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
+use value::{IntoValue, Value};
+use value_type::{HasValueType, ValueType};
+use validate::{IntoF64, Validator};
+#[cfg(feature = "regex")]
+use regex::Regex;
 
 /// Schema sets the structure and and default values of your configuration
 /// sources. A schema must be given when creating instances of `unison::Config`.
 #[derive(Debug)]
 pub struct Schema {
-  paths: HashMap<String, Box<Any + Send>>,
+  paths: HashMap<String, SchemaPath>,
+  subcommands: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+struct SchemaPath {
+  type_id: TypeId,
+  value_type: Box<dyn ValueType>,
+  default: Option<Box<dyn Any + Send>>,
+  default_value: Value,
+  meta: PathMeta,
+  validators: Vec<Validator>,
+}
+
+/// Metadata describing a schema path. It is set with the metadata methods of
+/// `SchemaBuilder` and is used by `Config` and the loaders.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathMeta {
+  /// A human readable description of the path.
+  pub description: Option<String>,
+  /// Whether the path has no default and must be given a value by a loader.
+  pub required: bool,
+  /// Whether the path is deprecated. A warning is logged when a loader
+  /// collects a value for it.
+  pub deprecated: bool,
+  /// The path which replaces a deprecated path. Values collected for the
+  /// deprecated path are moved to the replacement.
+  pub replaced_by: Option<String>,
+  /// Whether the value of the path is a secret. Secret values are redacted
+  /// when the config is exported.
+  pub secret: bool,
+  /// The name of an environment variable which is read in addition to the
+  /// prefixed variable of the path.
+  pub env: Option<String>,
+  /// A single letter alias for the path's CLI flag, such as `p` for `-p`.
+  pub short: Option<char>,
+  /// The position of the path among the CLI's positional arguments, counted
+  /// from 0 in the order positional paths are added to the schema.
+  pub positional: Option<usize>,
 }
 
 impl Schema {
   /// Creates a new schema builder which can be used to configure and build a
   /// new schema.
+  #[allow(clippy::new_ret_no_self)]
   pub fn new() -> SchemaBuilder {
     SchemaBuilder {
       paths: HashMap::new(),
+      subcommands: Vec::new(),
+      last_path: None,
     }
   }
 
@@ -22,36 +68,429 @@ impl Schema {
     self.paths.contains_key(path_name)
   }
 
+  /// Returns the names of every path in the schema, sorted.
+  pub fn path_names(&self) -> Vec<&str> {
+    let mut path_names: Vec<&str> = self.paths.keys().map(|p| p.as_str()).collect();
+    path_names.sort();
+    path_names
+  }
+
+  /// Returns a boolean indicating if the schema contains a given path or any
+  /// of its parents. Values below a path, such as the entries of a map, are
+  /// covered by that path.
+  pub fn covers_path(&self, path_name: &str) -> bool {
+    let mut path = path_name;
+    loop {
+      if self.has_path(path) {
+        return true;
+      }
+      match path.rfind('.') {
+        Some(index) => path = &path[..index],
+        None => return false,
+      }
+    }
+  }
+
+  /// Returns the schema path closest to a given unknown path, if any is close
+  /// enough to be a likely typo.
+  pub fn suggest_path(&self, path_name: &str) -> Option<&str> {
+    let max_distance = (path_name.chars().count() / 3).clamp(1, 3);
+    self
+      .path_names()
+      .into_iter()
+      .map(|p| (edit_distance(path_name, p), p))
+      .filter(|(d, _)| *d <= max_distance)
+      .min_by_key(|(d, _)| *d)
+      .map(|(_, p)| p)
+  }
+
   /// Returns a boolean indicating if the given schema path is the same type
   /// as the given type argument `C`.
   pub fn path_is_type<C: Any + Send>(&self, path_name: &str) -> bool {
     match self.paths.get(path_name) {
-      Some(p) => p.is::<C>(),
+      Some(p) => p.type_id == TypeId::of::<C>(),
       None => false,
     }
   }
 
+  /// Returns the value type of the given path if present. The value type is
+  /// used to parse and cast values collected for the path. Entries of map
+  /// paths have the entry type of the map, so `labels.env` has a type when
+  /// `labels` is a map.
+  pub fn path_type(&self, path_name: &str) -> Option<&dyn ValueType> {
+    if let Some(path) = self.paths.get(path_name) {
+      return Some(&*path.value_type);
+    }
+    let (parent, _) = path_name.rsplit_once('.')?;
+    self.paths.get(parent)?.value_type.entry_type()
+  }
+
   /// Returns the option wrapped default value at the given path if present.
+  /// Required paths have no default value.
   pub fn path_default<C: Any + Send>(&self, path_name: &str) -> Option<&C> {
-    self.paths.get(path_name)?.downcast_ref::<C>()
+    self.paths.get(path_name)?.default.as_ref()?.downcast_ref::<C>()
+  }
+
+  /// Returns the metadata of the given path if present.
+  pub fn path_meta(&self, path_name: &str) -> Option<&PathMeta> {
+    self.paths.get(path_name).map(|p| &p.meta)
+  }
+
+  /// Returns the validators of the given path. Paths without validators, and
+  /// paths missing from the schema, return an empty slice.
+  pub fn path_validators(&self, path_name: &str) -> &[Validator] {
+    match self.paths.get(path_name) {
+      Some(p) => &p.validators,
+      None => &[],
+    }
+  }
+
+  /// Returns the path with the given short CLI flag alias if present.
+  pub fn path_for_short(&self, short: char) -> Option<&str> {
+    self
+      .paths
+      .iter()
+      .find(|(_, p)| p.meta.short == Some(short))
+      .map(|(path_name, _)| path_name.as_str())
+  }
+
+  /// Returns the positional paths of the schema in the order of their
+  /// positions.
+  pub fn positional_paths(&self) -> Vec<&str> {
+    let mut paths: Vec<_> = self
+      .paths
+      .iter()
+      .filter_map(|(path_name, p)| Some((p.meta.positional?, path_name.as_str())))
+      .collect();
+    paths.sort();
+    paths.into_iter().map(|(_, path_name)| path_name).collect()
+  }
+
+  /// Returns the name and description of every subcommand in the schema, in
+  /// the order they were added.
+  pub fn subcommands(&self) -> Vec<(&str, &str)> {
+    self
+      .subcommands
+      .iter()
+      .map(|(name, description)| (name.as_str(), description.as_str()))
+      .collect()
+  }
+
+  /// Returns the subcommand whose subtree contains the given path, if any.
+  pub fn subcommand_of(&self, path_name: &str) -> Option<&str> {
+    self
+      .subcommands
+      .iter()
+      .map(|(name, _)| name.as_str())
+      .find(|name| path_name.starts_with(name) && path_name[name.len()..].starts_with('.'))
+  }
+
+  /// Returns a value tree containing the default value of every path in the
+  /// schema.
+  pub fn defaults(&self) -> Value {
+    let mut values = Value::HashMap(HashMap::new());
+    for (path_name, path) in self.paths.iter() {
+      if path.default.is_some() {
+        values.set_path(path_name, path.default_value.clone());
+      }
+    }
+    values
   }
 }
 
 /// Used to configure and build `unision::Schema` instances.
+///
+/// The metadata and validator methods apply to the path most recently added
+/// to the builder, and panic if no path has been added yet.
+///
+/// ```rust
+/// # use unison::*;
+/// let schema = Schema::new()
+///   .path("server.port", 8000u32)
+///   .describe("The port to listen on")
+///   .required_path::<String>("db.password")
+///   .secret()
+///   .env("DATABASE_PASSWORD")
+///   .build();
+/// # assert!(schema.path_meta("db.password").unwrap().required);
+/// ```
 pub struct SchemaBuilder {
-  paths: HashMap<String, Box<Any + Send>>,
+  paths: HashMap<String, SchemaPath>,
+  subcommands: Vec<(String, String)>,
+  last_path: Option<String>,
 }
 
 impl SchemaBuilder {
-  /// Adds a path with a default value to the schema to be built.
-  pub fn path<K: Any + Send>(mut self, path_name: &str, default_value: K) -> Self {
-    self.paths.insert(path_name.into(), Box::new(default_value));
+  /// Adds a path with a default value to the schema to be built. The value
+  /// type of the path is the value type of the default.
+  pub fn path<K>(self, path_name: &str, default_value: K) -> Self
+  where
+    K: Any + Send + Clone + IntoValue + HasValueType,
+  {
+    self.path_with_type(path_name, default_value, K::value_type())
+  }
+
+  /// Adds a path with a default value and a given value type to the schema
+  /// to be built. This allows types without a known value type to be used.
+  pub fn path_with_type<K>(self, path_name: &str, default_value: K, value_type: Box<dyn ValueType>) -> Self
+  where
+    K: Any + Send + Clone + IntoValue,
+  {
+    self.add_path(
+      path_name,
+      SchemaPath {
+        type_id: TypeId::of::<K>(),
+        value_type,
+        default_value: default_value.clone().into_value(),
+        default: Some(Box::new(default_value)),
+        meta: PathMeta::default(),
+        validators: Vec::new(),
+      },
+    )
+  }
+
+  /// Adds a path without a default value to the schema to be built. A loader
+  /// must collect a value for the path or `Config::init` will fail.
+  pub fn required_path<K: Any + Send + HasValueType>(self, path_name: &str) -> Self {
+    self.add_path(
+      path_name,
+      SchemaPath {
+        type_id: TypeId::of::<K>(),
+        value_type: K::value_type(),
+        default_value: Value::None,
+        default: None,
+        meta: PathMeta {
+          required: true,
+          ..PathMeta::default()
+        },
+        validators: Vec::new(),
+      },
+    )
+  }
+
+  /// Sets the description of the last added path.
+  pub fn describe<D: Into<String>>(mut self, description: D) -> Self {
+    self.last_meta().description = Some(description.into());
     self
   }
 
+  /// Marks the last added path as deprecated.
+  pub fn deprecated(mut self) -> Self {
+    self.last_meta().deprecated = true;
+    self
+  }
+
+  /// Marks the last added path as deprecated in favour of the given path.
+  pub fn replaced_by(mut self, path_name: &str) -> Self {
+    let meta = self.last_meta();
+    meta.deprecated = true;
+    meta.replaced_by = Some(path_name.to_owned());
+    self
+  }
+
+  /// Marks the value of the last added path as a secret.
+  pub fn secret(mut self) -> Self {
+    self.last_meta().secret = true;
+    self
+  }
+
+  /// Sets an environment variable to read the last added path from.
+  pub fn env(mut self, name: &str) -> Self {
+    self.last_meta().env = Some(name.to_owned());
+    self
+  }
+
+  /// Sets a single letter alias for the CLI flag of the last added path.
+  pub fn short(mut self, short: char) -> Self {
+    self.last_meta().short = Some(short);
+    self
+  }
+
+  /// Makes the last added path the next positional CLI argument. Positions
+  /// follow the order in which paths are made positional.
+  pub fn positional(mut self) -> Self {
+    let position = self.paths.values().filter(|p| p.meta.positional.is_some()).count();
+    self.last_meta().positional = Some(position);
+    self
+  }
+
+  /// Adds a subcommand to the schema to be built. The paths below the
+  /// subcommand's name, such as `serve.port` for `serve`, form its subtree
+  /// and are only read from the CLI when the subcommand is given.
+  pub fn subcommand<D: Into<String>>(mut self, name: &str, description: D) -> Self {
+    self.subcommands.push((name.to_owned(), description.into()));
+    self
+  }
+
+  /// Adds a validator to the last added path.
+  pub fn validator(mut self, validator: Validator) -> Self {
+    self.last_path_mut().validators.push(validator);
+    self
+  }
+
+  /// Adds a validator to the last added path rejecting numbers less than
+  /// the given minimum.
+  pub fn min<N: IntoF64>(self, min: N) -> Self {
+    self.validator(Validator::min(min))
+  }
+
+  /// Adds a validator to the last added path rejecting numbers greater than
+  /// the given maximum.
+  pub fn max<N: IntoF64>(self, max: N) -> Self {
+    self.validator(Validator::max(max))
+  }
+
+  /// Adds a validator to the last added path rejecting strings and lists
+  /// with a length outside of the given bounds.
+  pub fn length(self, min: usize, max: usize) -> Self {
+    self.validator(Validator::length(min, max))
+  }
+
+  /// Adds a validator to the last added path rejecting strings which don't
+  /// match the given pattern. Requires the `regex` feature.
+  #[cfg(feature = "regex")]
+  pub fn pattern(self, pattern: Regex) -> Self {
+    self.validator(Validator::pattern(pattern))
+  }
+
+  /// Adds a validator to the last added path rejecting any value not in the
+  /// given set. The allowed values are cast with the path's value type, so
+  /// `vec![80, 443]` can be given for a `u16` path.
+  pub fn one_of<V: IntoValue>(mut self, allowed: Vec<V>) -> Self {
+    let allowed = {
+      let value_type = &self.last_path_mut().value_type;
+      allowed
+        .into_iter()
+        .map(|v| v.into_value())
+        .map(|v| value_type.cast(v.clone()).unwrap_or(v))
+        .collect()
+    };
+    self.validator(Validator::one_of(allowed))
+  }
+
+  /// Adds a validator to the last added path from a closure.
+  pub fn validate_with<F>(self, check: F) -> Self
+  where
+    F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+  {
+    self.validator(Validator::new(check))
+  }
+
   /// Builds and returns a schema from the paths set on the builder.
   pub fn build(self) -> Schema {
-    Schema { paths: self.paths }
+    Schema {
+      paths: self.paths,
+      subcommands: self.subcommands,
+    }
+  }
+
+  fn add_path(mut self, path_name: &str, path: SchemaPath) -> Self {
+    self.paths.insert(path_name.into(), path);
+    self.last_path = Some(path_name.into());
+    self
+  }
+
+  /// Removes the defaults of a path and the paths below it.
+  fn without_defaults(mut self, path_name: &str) -> Self {
+    for (name, path) in self.paths.iter_mut() {
+      let below = name.starts_with(path_name) && name[path_name.len()..].starts_with('.');
+      if path_name.is_empty() || name == path_name || below {
+        path.default = None;
+        path.default_value = Value::None;
+      }
+    }
+    self
+  }
+
+  fn last_meta(&mut self) -> &mut PathMeta {
+    &mut self.last_path_mut().meta
+  }
+
+  fn last_path_mut(&mut self) -> &mut SchemaPath {
+    let path_name = self
+      .last_path
+      .as_ref()
+      .expect("Schema path metadata must be set after adding a path");
+    self.paths.get_mut(path_name).unwrap()
+  }
+}
+
+/// Returns the number of single character insertions, deletions, and
+/// substitutions needed to turn one string into another.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut distances: Vec<usize> = (0..=b.len()).collect();
+  for (i, a_char) in a.chars().enumerate() {
+    let mut previous = distances[0];
+    distances[0] = i + 1;
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = previous + if a_char == *b_char { 0 } else { 1 };
+      previous = distances[j + 1];
+      distances[j + 1] = substitution.min(previous + 1).min(distances[j] + 1);
+    }
+  }
+  distances[b.len()]
+}
+
+/// Implemented by types which can add their own paths to a schema. It is
+/// implemented for every type which can be used as a schema default, and can
+/// be derived for config structs with `#[derive(UnisonSchema)]` when the
+/// `derive` feature is enabled. A derived struct adds a path for each of its
+/// fields, nested below the given path, so the struct and its schema can't
+/// drift apart.
+pub trait UnisonSchema: Sized {
+  /// Adds the paths of the type to the given schema builder at a given path,
+  /// using the given value for their defaults. An empty path refers to the
+  /// root of the schema.
+  fn add_schema_paths(builder: SchemaBuilder, path_name: &str, default_value: Self) -> SchemaBuilder;
+
+  /// Builds a schema for the type using its `Default` implementation for the
+  /// default values.
+  fn schema() -> Schema
+  where
+    Self: Default,
+  {
+    Self::add_schema_paths(Schema::new(), "", Self::default()).build()
+  }
+}
+
+macro_rules! define_schema_types {
+  ($($type:ty),*) => {
+    $(
+      impl UnisonSchema for $type {
+        fn add_schema_paths(builder: SchemaBuilder, path_name: &str, default_value: Self) -> SchemaBuilder {
+          builder.path(path_name, default_value)
+        }
+      }
+    )*
+  };
+}
+
+define_schema_types!(bool, usize, isize, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, String);
+#[cfg(feature = "int128")]
+define_schema_types!(u128, i128);
+
+impl<T: Any + Send + Clone + IntoValue + HasValueType> UnisonSchema for Vec<T> {
+  fn add_schema_paths(builder: SchemaBuilder, path_name: &str, default_value: Self) -> SchemaBuilder {
+    builder.path(path_name, default_value)
+  }
+}
+
+/// Optional values add the paths of their inner type. When the default is
+/// `None` the paths have no default, without being required, so they are
+/// only set when a loader collects a value for them.
+impl<T: UnisonSchema + Default> UnisonSchema for Option<T> {
+  fn add_schema_paths(builder: SchemaBuilder, path_name: &str, default_value: Self) -> SchemaBuilder {
+    match default_value {
+      Some(default_value) => T::add_schema_paths(builder, path_name, default_value),
+      None => T::add_schema_paths(builder, path_name, T::default()).without_defaults(path_name),
+    }
+  }
+}
+
+impl<T: Any + Send + Clone + IntoValue + HasValueType> UnisonSchema for HashMap<String, T> {
+  fn add_schema_paths(builder: SchemaBuilder, path_name: &str, default_value: Self) -> SchemaBuilder {
+    builder.path(path_name, default_value)
   }
 }
 
@@ -76,6 +515,142 @@ mod tests {
     assert_eq!(*default_value, 3000);
   }
 
+  #[test]
+  fn can_get_schema_defaults() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path("server.url", "http://test.url")
+      .build();
+
+    let defaults = schema.defaults();
+
+    assert_eq!(*defaults.get_path("server.port"), Value::U32(3000));
+    assert_eq!(*defaults.get_path("server.url"), Value::String("http://test.url".into()));
+  }
+
+  #[test]
+  fn can_add_schema_paths_from_types() {
+    let builder = u32::add_schema_paths(Schema::new(), "server.port", 3000);
+    let schema = Vec::<u32>::add_schema_paths(builder, "server.peers", vec![1]).build();
+
+    assert!(schema.path_is_type::<u32>("server.port"));
+    assert!(schema.path_is_type::<Vec<u32>>("server.peers"));
+    assert_eq!(schema.path_default::<Vec<u32>>("server.peers"), Some(&vec![1]));
+  }
+
+  #[test]
+  fn can_set_path_metadata() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .describe("The port to listen on")
+      .path::<u32>("port", 3000)
+      .replaced_by("server.port")
+      .required_path::<String>("db.password")
+      .secret()
+      .env("DATABASE_PASSWORD")
+      .build();
+
+    let port = schema.path_meta("server.port").unwrap();
+    assert_eq!(port.description, Some("The port to listen on".into()));
+    let old_port = schema.path_meta("port").unwrap();
+    assert!(old_port.deprecated);
+    assert_eq!(old_port.replaced_by, Some("server.port".into()));
+    let password = schema.path_meta("db.password").unwrap();
+    assert!(password.required && password.secret);
+    assert_eq!(password.env, Some("DATABASE_PASSWORD".into()));
+  }
+
+  #[test]
+  fn can_add_positional_paths_and_subcommands() {
+    let schema = Schema::new()
+      .subcommand("serve", "Starts the server")
+      .path::<String>("output", "-".into())
+      .positional()
+      .required_path::<String>("input")
+      .positional()
+      .path::<u32>("serve.port", 3000)
+      .build();
+
+    assert_eq!(schema.positional_paths(), vec!["output", "input"]);
+    assert_eq!(schema.subcommands(), vec![("serve", "Starts the server")]);
+    assert_eq!(schema.subcommand_of("serve.port"), Some("serve"));
+    assert_eq!(schema.subcommand_of("server.port"), None);
+  }
+
+  #[test]
+  fn can_cast_allowed_values_to_path_type() {
+    let schema = Schema::new().path::<u16>("port", 8000).one_of(vec![80, 443]).build();
+    let validator = &schema.path_validators("port")[0];
+
+    assert_eq!(validator.validate(&Value::U16(443)), Ok(()));
+    assert_eq!(validator.validate(&Value::U16(8000)), Err("must be one of 80, 443".into()));
+  }
+
+  #[test]
+  fn can_add_required_path_without_default() {
+    let schema = Schema::new().required_path::<u32>("server.port").build();
+
+    assert!(schema.path_is_type::<u32>("server.port"));
+    assert_eq!(schema.path_default::<u32>("server.port"), None);
+    assert_eq!(*schema.defaults().get_path("server.port"), Value::None);
+  }
+
+  #[test]
+  fn can_add_path_with_custom_value_type() {
+    #[derive(Debug)]
+    struct PortType;
+
+    impl ValueType for PortType {
+      fn name(&self) -> &'static str {
+        "port"
+      }
+
+      fn parse(&self, raw: &str) -> Option<Value> {
+        raw.parse::<u16>().ok().filter(|p| *p > 0).map(Value::U16)
+      }
+    }
+
+    let schema = Schema::new()
+      .path_with_type("server.port", 3000u16, Box::new(PortType))
+      .build();
+    let port_type = schema.path_type("server.port").unwrap();
+
+    assert_eq!(port_type.parse("8000"), Some(Value::U16(8000)));
+    assert_eq!(port_type.parse("0"), None);
+  }
+
+  #[test]
+  fn can_suggest_path_for_typo() {
+    let schema = Schema::new()
+      .path::<u32>("server.port", 3000)
+      .path::<String>("server.host", "localhost".into())
+      .build();
+
+    assert_eq!(schema.suggest_path("sever.port"), Some("server.port"));
+    assert_eq!(schema.suggest_path("client.timeout"), None);
+  }
+
+  #[test]
+  fn can_check_if_schema_covers_path() {
+    let schema = Schema::new()
+      .path::<HashMap<String, Value>>("labels", HashMap::new())
+      .build();
+
+    assert!(schema.covers_path("labels.team"));
+    assert!(!schema.covers_path("other.team"));
+  }
+
+  #[test]
+  fn can_get_type_of_map_entries() {
+    let schema = Schema::new()
+      .path::<HashMap<String, u32>>("limits", HashMap::new())
+      .build();
+
+    assert_eq!(schema.path_type("limits.cpu").unwrap().parse("2"), Some(Value::U32(2)));
+    assert!(schema.path_type("limits.cpu.max").is_none());
+    assert!(!schema.has_path("limits.cpu"));
+  }
+
   #[test]
   fn can_check_schema_path_type() {
     let schema = Schema::new().path::<u32>("server.port", 3000).build();
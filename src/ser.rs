@@ -0,0 +1,94 @@
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use value::Value;
+
+impl Serialize for Value {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match *self {
+      Value::Bool(b) => serializer.serialize_bool(b),
+
+      Value::USize(n) => serializer.serialize_u64(n as u64),
+      Value::ISize(n) => serializer.serialize_i64(n as i64),
+
+      Value::U8(n) => serializer.serialize_u8(n),
+      Value::U16(n) => serializer.serialize_u16(n),
+      Value::U32(n) => serializer.serialize_u32(n),
+      Value::U64(n) => serializer.serialize_u64(n),
+      #[cfg(feature = "int128")]
+      Value::U128(n) => serializer.serialize_u128(n),
+
+      Value::I8(n) => serializer.serialize_i8(n),
+      Value::I16(n) => serializer.serialize_i16(n),
+      Value::I32(n) => serializer.serialize_i32(n),
+      Value::I64(n) => serializer.serialize_i64(n),
+      #[cfg(feature = "int128")]
+      Value::I128(n) => serializer.serialize_i128(n),
+
+      Value::F32(n) => serializer.serialize_f32(n),
+      Value::F64(n) => serializer.serialize_f64(n),
+
+      Value::String(ref s) => serializer.serialize_str(s),
+
+      Value::HashMap(ref h) => {
+        // NOTE: Keys are sorted so output is stable. Hash maps are placed
+        // after all other values as formats like TOML require tables to come
+        // last. Empty values are left out as not every format supports them.
+        let mut entries: Vec<_> = h.iter().filter(|&(_, v)| *v != Value::None).collect();
+        entries.sort_by_key(|&(k, v)| (v.is_hash_map(), k));
+
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (key, value) in entries {
+          map.serialize_entry(key, value)?;
+        }
+        map.end()
+      }
+      Value::Vec(ref v) => {
+        let mut seq = serializer.serialize_seq(Some(v.len()))?;
+        for value in v {
+          seq.serialize_element(value)?;
+        }
+        seq.end()
+      }
+
+      Value::None => serializer.serialize_none(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use format::Format;
+  use value::Value;
+
+  fn config_value() -> Value {
+    let mut value = Value::HashMap(HashMap::new());
+    value.set_path("server.port", Value::U32(8000));
+    value.set_path("server.peers", Value::Vec(vec![Value::String("a".into())]));
+    value.set_path("name", Value::String("my_app".into()));
+    value
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn can_serialize_value_to_json() {
+    let json = Format::Json.serialize(&config_value()).unwrap();
+
+    assert_eq!(json, "{\n  \"name\": \"my_app\",\n  \"server\": {\n    \"peers\": [\n      \"a\"\n    ],\n    \"port\": 8000\n  }\n}");
+  }
+
+  #[cfg(feature = "toml")]
+  #[test]
+  fn can_serialize_value_to_toml() {
+    let toml = Format::Toml.serialize(&config_value()).unwrap();
+
+    assert_eq!(toml, "name = \"my_app\"\n\n[server]\npeers = [\"a\"]\nport = 8000\n");
+  }
+
+  #[cfg(feature = "yaml")]
+  #[test]
+  fn can_serialize_value_to_yaml() {
+    let yaml = Format::Yaml.serialize(&config_value()).unwrap();
+
+    assert_eq!(Format::Yaml.parse(&yaml).unwrap().get_path("server.port"), &Value::I64(8000));
+  }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::path::PathBuf;
+use value::Value;
+
+/// Describes where a config value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+  /// A command line argument. The index is the position of the argument
+  /// within `std::env::args`, where `0` is the program name.
+  CliArg { index: usize, arg: String },
+  /// An environment variable.
+  EnvVar { name: String },
+  /// A configuration file. The position is the line and column of the key
+  /// within the file, both starting at `1`, when it could be determined.
+  File { path: PathBuf, position: Option<(usize, usize)> },
+  /// The default value of the schema.
+  Default,
+  /// An override set with `Config::set`.
+  Override,
+  /// A source the layer did not report.
+  Unknown,
+}
+
+impl fmt::Display for Source {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Source::CliArg { index, ref arg } => write!(f, "command line argument {} `{}`", index, arg),
+      Source::EnvVar { ref name } => write!(f, "environment variable `{}`", name),
+      Source::File {
+        ref path,
+        position: Some((line, column)),
+      } => write!(f, "{}:{}:{}", path.display(), line, column),
+      Source::File { ref path, .. } => write!(f, "{}", path.display()),
+      Source::Default => write!(f, "schema default"),
+      Source::Override => write!(f, "override set with `Config::set`"),
+      Source::Unknown => write!(f, "unknown source"),
+    }
+  }
+}
+
+/// A value found at a config path within a single layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+  /// The name of the layer containing the value.
+  pub layer: String,
+  /// The name of the loader which produced the layer, if any.
+  pub loader: Option<String>,
+  /// Where the loader found the value.
+  pub source: Source,
+  /// The value itself.
+  pub value: Value,
+}
+
+impl fmt::Display for Provenance {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?} from layer `{}`", self.value, self.layer)?;
+    if let Some(ref loader) = self.loader {
+      write!(f, " (loader `{}`)", loader)?;
+    }
+    write!(f, ", {}", self.source)
+  }
+}
+
+/// Explains why a config path has the value it has. Returned by
+/// `Config::explain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+  /// The explained config path.
+  pub path: String,
+  /// The value which is returned for the path.
+  pub winner: Provenance,
+  /// The values from layers with a lower precedence that were hidden by the
+  /// winning value, ordered from the highest precedence to the lowest.
+  pub overridden: Vec<Provenance>,
+}
+
+impl fmt::Display for Explanation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} = {}", self.path, self.winner)?;
+    for provenance in self.overridden.iter() {
+      write!(f, "\n  overrides {}", provenance)?;
+    }
+    Ok(())
+  }
+}
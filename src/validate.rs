@@ -0,0 +1,197 @@
+use std::fmt;
+#[cfg(feature = "regex")]
+use regex::Regex;
+use value::Value;
+
+type Check = dyn Fn(&Value) -> Result<(), String> + Send + Sync;
+
+/// Checks the value of a schema path. Validators are attached to paths with
+/// `SchemaBuilder::validator` or one of its shorthand methods, and are run by
+/// `Config::init` against the merged value of each path. A validator returns
+/// a message describing why the value is invalid.
+pub struct Validator {
+  check: Box<Check>,
+}
+
+impl Validator {
+  /// Creates a validator from a closure.
+  pub fn new<F>(check: F) -> Self
+  where
+    F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+  {
+    Self {
+      check: Box::new(check),
+    }
+  }
+
+  /// Creates a validator rejecting numbers less than the given minimum.
+  pub fn min<N: IntoF64>(min: N) -> Self {
+    let min = min.into_f64();
+    Self::new(move |v| match as_f64(v) {
+      Some(n) if n < min => Err(format!("must be at least {}", min)),
+      _ => Ok(()),
+    })
+  }
+
+  /// Creates a validator rejecting numbers greater than the given maximum.
+  pub fn max<N: IntoF64>(max: N) -> Self {
+    let max = max.into_f64();
+    Self::new(move |v| match as_f64(v) {
+      Some(n) if n > max => Err(format!("must be at most {}", max)),
+      _ => Ok(()),
+    })
+  }
+
+  /// Creates a validator rejecting strings and lists with a length outside
+  /// of the given bounds. The length of a string is its number of characters.
+  pub fn length(min: usize, max: usize) -> Self {
+    Self::new(move |v| {
+      let length = match *v {
+        Value::String(ref s) => s.chars().count(),
+        Value::Vec(ref v) => v.len(),
+        _ => return Ok(()),
+      };
+      if length < min || length > max {
+        Err(format!("must have a length between {} and {}", min, max))
+      } else {
+        Ok(())
+      }
+    })
+  }
+
+  /// Creates a validator rejecting strings which don't match the given
+  /// pattern. Requires the `regex` feature.
+  #[cfg(feature = "regex")]
+  pub fn pattern(pattern: Regex) -> Self {
+    Self::new(move |v| match *v {
+      Value::String(ref s) if !pattern.is_match(s) => Err(format!("must match the pattern {}", pattern)),
+      _ => Ok(()),
+    })
+  }
+
+  /// Creates a validator rejecting any value not in the given set. Numbers
+  /// are compared by value, so `Value::I32(80)` allows `Value::U16(80)`.
+  pub fn one_of(allowed: Vec<Value>) -> Self {
+    Self::new(move |v| {
+      let number = as_f64(v);
+      if allowed.iter().any(|a| a == v || number.is_some() && as_f64(a) == number) {
+        return Ok(());
+      }
+      let allowed: Vec<String> = allowed
+        .iter()
+        .map(|a| a.to_raw().unwrap_or_else(|| format!("{:?}", a)))
+        .collect();
+      Err(format!("must be one of {}", allowed.join(", ")))
+    })
+  }
+
+  /// Checks a value, returning a message describing why it's invalid.
+  pub fn validate(&self, value: &Value) -> Result<(), String> {
+    (self.check)(value)
+  }
+}
+
+impl fmt::Debug for Validator {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("Validator")
+  }
+}
+
+/// Implemented by the number types which can be used as the bounds of
+/// `Validator::min` and `Validator::max`. Unlike `Into<f64>` it includes the
+/// 64 bit and pointer sized integers, which are converted with `as`.
+pub trait IntoF64 {
+  /// Converts the number into an `f64`.
+  fn into_f64(self) -> f64;
+}
+
+macro_rules! define_into_f64 {
+  ($($type:ty),*) => {
+    $(
+      impl IntoF64 for $type {
+        fn into_f64(self) -> f64 {
+          self as f64
+        }
+      }
+    )*
+  };
+}
+
+define_into_f64!(usize, isize, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+#[cfg(feature = "int128")]
+define_into_f64!(u128, i128);
+
+fn as_f64(value: &Value) -> Option<f64> {
+  match *value {
+    Value::USize(n) => Some(n as f64),
+    Value::ISize(n) => Some(n as f64),
+    Value::U8(n) => Some(f64::from(n)),
+    Value::U16(n) => Some(f64::from(n)),
+    Value::U32(n) => Some(f64::from(n)),
+    Value::U64(n) => Some(n as f64),
+    #[cfg(feature = "int128")]
+    Value::U128(n) => Some(n as f64),
+    Value::I8(n) => Some(f64::from(n)),
+    Value::I16(n) => Some(f64::from(n)),
+    Value::I32(n) => Some(f64::from(n)),
+    Value::I64(n) => Some(n as f64),
+    #[cfg(feature = "int128")]
+    Value::I128(n) => Some(n as f64),
+    Value::F32(n) => Some(f64::from(n)),
+    Value::F64(n) => Some(n),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn can_validate_numeric_range() {
+    let min = Validator::min(1);
+    let max = Validator::max(65535);
+
+    assert_eq!(min.validate(&Value::U32(0)), Err("must be at least 1".into()));
+    assert_eq!(max.validate(&Value::U32(99999)), Err("must be at most 65535".into()));
+    assert_eq!(max.validate(&Value::U32(8000)), Ok(()));
+  }
+
+  #[test]
+  fn can_validate_range_with_64_bit_bounds() {
+    let max = Validator::max(u64::from(u32::MAX) + 1);
+
+    assert_eq!(max.validate(&Value::U64(1 << 32)), Ok(()));
+    assert!(max.validate(&Value::U64((1 << 32) + 1)).is_err());
+    assert!(Validator::min(-1i64).validate(&Value::I64(-2)).is_err());
+    assert!(Validator::min(1usize).validate(&Value::USize(0)).is_err());
+  }
+
+  #[test]
+  fn can_validate_allowed_values() {
+    let validator = Validator::one_of(vec![Value::String("debug".into()), Value::String("info".into())]);
+
+    assert_eq!(validator.validate(&Value::String("info".into())), Ok(()));
+    assert_eq!(
+      validator.validate(&Value::String("loud".into())),
+      Err("must be one of debug, info".into())
+    );
+  }
+
+  #[test]
+  fn can_validate_allowed_numbers_of_any_type() {
+    let validator = Validator::one_of(vec![Value::I32(80), Value::I32(443)]);
+
+    assert_eq!(validator.validate(&Value::U16(80)), Ok(()));
+    assert_eq!(validator.validate(&Value::U16(8000)), Err("must be one of 80, 443".into()));
+  }
+
+  #[test]
+  #[cfg(feature = "regex")]
+  fn can_validate_pattern() {
+    let validator = Validator::pattern(Regex::new("^[a-z]+$").unwrap());
+
+    assert!(validator.validate(&Value::String("abc".into())).is_ok());
+    assert!(validator.validate(&Value::String("ABC".into())).is_err());
+  }
+}
@@ -1,11 +1,12 @@
 use std::cmp::Eq;
-use std::mem;
 use std::hash::Hash;
 use std::str::FromStr;
 use std::collections::HashMap;
-use failure::{Error, Fail};
+use std::fmt::{self, Debug, Display};
+use failure::{err_msg, Error, Fail};
 use schema::Schema;
 use config::ConfigError;
+use errors::ConfigErrors;
 
 /// A enum value used to contain configuration values.
 #[derive(Debug, Clone, PartialEq)]
@@ -46,70 +47,164 @@ impl Value {
     path_name: &str,
     schema: &Schema,
   ) -> Result<Value, ConfigError> {
-    match value {
-      // bool
-      None if schema.path_is_type::<bool>(path_name) => Ok(Value::Bool(true)),
-
-      // word sized ints
-      Some(ref v) if schema.path_is_type::<usize>(path_name) => Ok(Value::USize(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "usize"))?)),
-      Some(ref v) if schema.path_is_type::<isize>(path_name) => Ok(Value::ISize(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "isize"))?)),
-
-      // unsigned ints
-      Some(ref v) if schema.path_is_type::<u8>(path_name) => Ok(Value::U8(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "u8"))?)),
-      Some(ref v) if schema.path_is_type::<u16>(path_name) => Ok(Value::U16(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "u16"))?)),
-      Some(ref v) if schema.path_is_type::<u32>(path_name) => Ok(Value::U32(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "u32"))?)),
-      Some(ref v) if schema.path_is_type::<u64>(path_name) => Ok(Value::U64(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "u64"))?)),
-      #[cfg(feature = "int128")]
-      Some(ref v) if schema.path_is_type::<u128>(path_name) =>
-      {
-        Ok(Value::U128(v.parse().map_err(|_| {
-          ConfigError::BadPathType(path_name.to_owned(), "u128")
-        })?))
+    let value_type = match schema.path_type(path_name) {
+      Some(value_type) => value_type,
+      None => {
+        return match value {
+          Some(ref v) if v == "_" => Ok(Value::None),
+          _ => Err(ConfigError::BadPathType(path_name.to_owned(), "_")),
+        }
       }
+    };
+
+    let parsed = match value {
+      Some(ref raw) => value_type.parse(raw),
+      None => value_type.flag_value(),
+    };
+    parsed.ok_or_else(|| ConfigError::BadPathType(path_name.to_owned(), value_type.name()))
+  }
+
+  /// Creates a value tree from a given loosely typed value tree, such as one
+  /// parsed from a configuration file. The schema is used to cast each value
+  /// to the correct type, and any value without a matching schema path is
+  /// dropped. The given path is the location of the value within the config,
+  /// an empty path refers to the root. Null values are treated as unset.
+  /// Every value which can't be cast is returned together as
+  /// `ConfigError::Errors`.
+  pub fn from_value_with_schema_and_path(
+    value: Value,
+    path_name: &str,
+    schema: &Schema,
+  ) -> Result<Value, ConfigError> {
+    if value == Value::None {
+      return Ok(Value::None);
+    }
+
+    if let Some(value_type) = schema.path_type(path_name) {
+      return value_type
+        .cast(value)
+        .ok_or_else(|| ConfigError::BadPathType(path_name.to_owned(), value_type.name()));
+    }
+
+    let value_hash_map = match value {
+      Value::HashMap(h) => h,
+      _ => return Ok(Value::None),
+    };
+
+    let mut hash_map = HashMap::new();
+    let mut errors = Vec::new();
+    for (key, value) in value_hash_map {
+      let child_path_name = if path_name.is_empty() {
+        key.clone()
+      } else {
+        format!("{}.{}", path_name, key)
+      };
+      match Self::from_value_with_schema_and_path(value, &child_path_name, schema) {
+        Ok(Value::None) => continue,
+        Ok(Value::HashMap(ref h)) if h.is_empty() => continue,
+        Ok(v) => hash_map.insert(key, v),
+        Err(e) => {
+          errors.extend(e.into_path_errors()?);
+          continue;
+        }
+      };
+    }
+
+    if !errors.is_empty() {
+      return Err(ConfigError::Errors(ConfigErrors(errors)));
+    }
+    Ok(Value::HashMap(hash_map))
+  }
+
+  pub(crate) fn to_raw(&self) -> Option<String> {
+    match *self {
+      Value::Bool(b) => Some(b.to_string()),
 
-      // signed ints
-      Some(ref v) if schema.path_is_type::<i8>(path_name) => Ok(Value::I8(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "i8"))?)),
-      Some(ref v) if schema.path_is_type::<i16>(path_name) => Ok(Value::I16(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "i16"))?)),
-      Some(ref v) if schema.path_is_type::<i32>(path_name) => Ok(Value::I32(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "i32"))?)),
-      Some(ref v) if schema.path_is_type::<i64>(path_name) => Ok(Value::I64(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "i64"))?)),
+      Value::USize(n) => Some(n.to_string()),
+      Value::ISize(n) => Some(n.to_string()),
+
+      Value::U8(n) => Some(n.to_string()),
+      Value::U16(n) => Some(n.to_string()),
+      Value::U32(n) => Some(n.to_string()),
+      Value::U64(n) => Some(n.to_string()),
       #[cfg(feature = "int128")]
-      Some(ref v) if schema.path_is_type::<i128>(path_name) =>
-      {
-        Ok(Value::I128(v.parse().map_err(|_| {
-          ConfigError::BadPathType(path_name.to_owned(), "i128")
-        })?))
-      }
+      Value::U128(n) => Some(n.to_string()),
+
+      Value::I8(n) => Some(n.to_string()),
+      Value::I16(n) => Some(n.to_string()),
+      Value::I32(n) => Some(n.to_string()),
+      Value::I64(n) => Some(n.to_string()),
+      #[cfg(feature = "int128")]
+      Value::I128(n) => Some(n.to_string()),
+
+      Value::F32(n) => Some(n.to_string()),
+      Value::F64(n) => Some(n.to_string()),
 
-      // floats
-      Some(ref v) if schema.path_is_type::<f32>(path_name) => Ok(Value::F32(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "f32"))?)),
-      Some(ref v) if schema.path_is_type::<f64>(path_name) => Ok(Value::F64(v.parse()
-        .map_err(|_| ConfigError::BadPathType(path_name.to_owned(), "f64"))?)),
+      Value::String(ref s) => Some(s.to_owned()),
 
-      // String
-      Some(ref v) if schema.path_is_type::<String>(path_name) => Ok(Value::String(v.to_owned())),
+      _ => None,
+    }
+  }
 
-      // Collections
-      Some(_) if schema.path_is_type::<HashMap<String, Value>>(path_name) => unimplemented!(),
-      Some(_) if schema.path_is_type::<Vec<Value>>(path_name) => unimplemented!(),
+  /// Returns the paths of every value in the tree which isn't a hash map.
+  pub fn leaf_paths(&self) -> Vec<String> {
+    let mut paths = Vec::new();
+    self.collect_leaf_paths("", &mut paths);
+    paths
+  }
 
-      // None
-      Some(ref v) if v == "_" => Ok(Value::None),
+  fn collect_leaf_paths(&self, path: &str, paths: &mut Vec<String>) {
+    match *self {
+      Value::HashMap(ref h) => {
+        for (key, value) in h {
+          let child_path = if path.is_empty() {
+            key.to_owned()
+          } else {
+            format!("{}.{}", path, key)
+          };
+          value.collect_leaf_paths(&child_path, paths);
+        }
+      }
+      Value::None => {}
+      _ => paths.push(path.to_owned()),
+    }
+  }
 
-      _ => Err(ConfigError::BadPathType(path_name.to_owned(), "_")),
+  /// Merges the given value tree into this one. Values in the given tree
+  /// replace values at the same path in this tree, and hash maps present in
+  /// both are merged recursively.
+  pub fn merge(&mut self, value: Value) {
+    match (self, value) {
+      (&mut Value::HashMap(ref mut target), Value::HashMap(source)) => {
+        for (key, value) in source {
+          match target.get_mut(&key) {
+            Some(existing) => {
+              existing.merge(value);
+              continue;
+            }
+            None => target.insert(key, value),
+          };
+        }
+      }
+      (_, Value::None) => {}
+      (target, value) => *target = value,
     }
   }
 
+  /// Merges a value into the tree at a given path. Unlike `set_path`, a hash
+  /// map merges with any hash map already at the path rather than replacing
+  /// it.
+  pub fn merge_path<P: AsRef<str>>(&mut self, path: P, value: Value) {
+    let mut tree = Value::HashMap(HashMap::new());
+    tree.set_path(path, value);
+    self.merge(tree);
+  }
+
+  /// Returns a boolean indicating if the value is a hash map.
+  pub fn is_hash_map(&self) -> bool {
+    matches!(*self, Value::HashMap(_))
+  }
+
   /// Gets the value at a given path relative the value instance get_path is
   /// called upon.
   pub fn get_path<P: AsRef<str>>(&self, path: P) -> &Value {
@@ -147,7 +242,7 @@ impl Value {
       let next_key = &path[0..key_end_index];
 
       if let Some(next_value) = self.get_key_mut(next_key) {
-        let trailing_path = &path[key_end_index..];
+        let trailing_path = &path[key_end_index + 1..];
         return next_value.set_path(trailing_path, value);
       }
 
@@ -173,7 +268,7 @@ impl Value {
       ref mut v => {
         let mut values = Value::HashMap(HashMap::new());
         values.set_key(key, value);
-        mem::replace(v, values);
+        *v = values;
       }
     };
   }
@@ -187,7 +282,7 @@ impl Value {
 
 /// FromValue allows casting a value into any type that implements it.
 pub trait FromValue: Sized {
-  fn from_value(Value) -> Result<Self, FromValueError>;
+  fn from_value(value: Value) -> Result<Self, FromValueError>;
 }
 
 /// IntoValue allows casting any type that implements it into a value.
@@ -195,6 +290,25 @@ pub trait IntoValue {
   fn into_value(self) -> Value;
 }
 
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Value::HashMap(ref h) => {
+        let mut keys: Vec<_> = h.keys().collect();
+        keys.sort();
+        let entries: Vec<_> = keys.into_iter().map(|k| format!("{} = {}", k, h[k])).collect();
+        write!(f, "{{{}}}", entries.join(", "))
+      }
+      Value::Vec(ref v) => {
+        let items: Vec<_> = v.iter().map(|v| v.to_string()).collect();
+        write!(f, "[{}]", items.join(", "))
+      }
+      Value::None => write!(f, "none"),
+      ref v => write!(f, "{}", v.to_raw().unwrap_or_default()),
+    }
+  }
+}
+
 impl FromValue for Value {
   fn from_value(value: Value) -> Result<Self, FromValueError> {
     Ok(value)
@@ -246,7 +360,7 @@ macro_rules! define_ints {
   };
 }
 
-impl<'a> FromValue for bool {
+impl FromValue for bool {
   fn from_value(value: Value) -> Result<Self, FromValueError> {
     match value {
       Value::Bool(b) => Ok(b),
@@ -257,7 +371,7 @@ impl<'a> FromValue for bool {
   }
 }
 
-impl<'a> IntoValue for bool {
+impl IntoValue for bool {
   fn into_value(self) -> Value {
     Value::Bool(self)
   }
@@ -283,7 +397,7 @@ define_ints!(i128, Value::I128);
 define_ints!(f32, Value::F32);
 define_ints!(f64, Value::F64);
 
-impl<'a> FromValue for String {
+impl FromValue for String {
   fn from_value(value: Value) -> Result<Self, FromValueError> {
     match value {
       Value::String(s) => Ok(s),
@@ -294,13 +408,13 @@ impl<'a> FromValue for String {
   }
 }
 
-impl<'a> IntoValue for String {
+impl IntoValue for String {
   fn into_value(self) -> Value {
     Value::String(self)
   }
 }
 
-impl<'a> IntoValue for &'a str {
+impl IntoValue for &str {
   fn into_value(self) -> Value {
     Value::String(self.to_owned())
   }
@@ -377,6 +491,24 @@ impl<T: IntoValue> IntoValue for Vec<T> {
   }
 }
 
+impl<T: FromValue> FromValue for Option<T> {
+  fn from_value(value: Value) -> Result<Self, FromValueError> {
+    match value {
+      Value::None => Ok(None),
+      v => Ok(Some(T::from_value(v)?)),
+    }
+  }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+  fn into_value(self) -> Value {
+    match self {
+      Some(v) => v.into_value(),
+      None => Value::None,
+    }
+  }
+}
+
 #[derive(Debug, Fail)]
 #[fail(display = "Missing path")]
 pub struct MissingPathError;
@@ -385,4 +517,71 @@ pub struct MissingPathError;
 pub enum FromValueError {
   #[fail(display = "Cannot cast value: {}", _0)] InvalidCast(#[fail(cause)] Error),
   #[fail(display = "Cannot parse key: {}", _0)] KeyParseError(#[fail(cause)] Error),
+  #[fail(display = "Missing field `{}`", _0)] MissingField(String),
+  #[fail(display = "Unknown variant `{}`", _0)] UnknownVariant(String),
+}
+
+impl FromValueError {
+  /// Creates an `InvalidCast` error with the given message.
+  pub fn invalid_cast<M: Display + Debug + Send + Sync + 'static>(message: M) -> Self {
+    FromValueError::InvalidCast(err_msg(message))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn can_set_sibling_paths() {
+    let mut value = Value::HashMap(HashMap::new());
+
+    value.set_path("server.port", Value::U32(8000));
+    value.set_path("server.host", Value::String("localhost".into()));
+
+    assert_eq!(*value.get_path("server.port"), Value::U32(8000));
+    assert_eq!(*value.get_path("server.host"), Value::String("localhost".into()));
+  }
+
+  #[test]
+  fn can_cast_value_tree_with_schema() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let mut server = HashMap::new();
+    server.insert("port".to_owned(), Value::I64(8000));
+    server.insert("unknown".to_owned(), Value::I64(8000));
+    let mut root = HashMap::new();
+    root.insert("server".to_owned(), Value::HashMap(server));
+
+    let value = Value::from_value_with_schema_and_path(Value::HashMap(root), "", &schema).unwrap();
+
+    assert_eq!(*value.get_path("server.port"), Value::U32(8000));
+    assert_eq!(*value.get_path("server.unknown"), Value::None);
+  }
+
+  #[test]
+  fn can_treat_null_values_as_unset() {
+    let schema = Schema::new().path::<u32>("server.port", 3000).build();
+    let mut server = HashMap::new();
+    server.insert("port".to_owned(), Value::None);
+    let mut root = HashMap::new();
+    root.insert("server".to_owned(), Value::HashMap(server));
+
+    let value = Value::from_value_with_schema_and_path(Value::HashMap(root), "", &schema).unwrap();
+
+    assert_eq!(*value.get_path("server.port"), Value::None);
+  }
+
+  #[test]
+  fn can_merge_value_trees() {
+    let mut value = Value::HashMap(HashMap::new());
+    value.set_path("server.port", Value::U32(8000));
+    value.set_path("server.host", Value::String("localhost".into()));
+    let mut other = Value::HashMap(HashMap::new());
+    other.set_path("server.port", Value::U32(9000));
+
+    value.merge(other);
+
+    assert_eq!(*value.get_path("server.port"), Value::U32(9000));
+    assert_eq!(*value.get_path("server.host"), Value::String("localhost".into()));
+  }
 }
@@ -0,0 +1,354 @@
+use std::fmt;
+use std::str::FromStr;
+use std::marker::PhantomData;
+use std::collections::HashMap;
+use value::{IntoValue, Value};
+
+/// Describes the type of the values at a schema path. A value type owns the
+/// parsing of raw strings, such as those given to the CLI and env loaders, and
+/// the casting of loosely typed values, such as those parsed from files.
+/// Returning `None` from either rejects the value.
+///
+/// Implement this trait to support types the crate doesn't know about, and
+/// give it to the schema with `SchemaBuilder::path_with_type`.
+pub trait ValueType: fmt::Debug + Send + Sync {
+  /// The name of the type, used in error messages.
+  fn name(&self) -> &'static str;
+
+  /// Parses a raw string into a value of the type.
+  fn parse(&self, raw: &str) -> Option<Value>;
+
+  /// Casts a loosely typed value into a value of the type. By default any
+  /// string or number is converted to a raw string and parsed.
+  fn cast(&self, value: Value) -> Option<Value> {
+    match value {
+      Value::String(s) => self.parse(&s),
+      v => self.parse(&v.to_raw()?),
+    }
+  }
+
+  /// The value used when the path is given without a value, such as a CLI
+  /// flag with nothing after it. By default a value is always expected.
+  fn flag_value(&self) -> Option<Value> {
+    None
+  }
+
+  /// The value used when the path is given as a negated CLI flag, such as
+  /// `--no-verbose`. By default flags can't be negated.
+  fn negated_flag_value(&self) -> Option<Value> {
+    None
+  }
+
+  /// The value type of the entries of a map type. Entries can be given at
+  /// their own path below the map's path, such as `labels.env`. By default
+  /// the type has no entries.
+  fn entry_type(&self) -> Option<&dyn ValueType> {
+    None
+  }
+
+  /// The value type of the items of a list type. Items can be given one at
+  /// a time, such as positional CLI arguments, and are parsed whole with this
+  /// type. By default the type has no items.
+  fn item_type(&self) -> Option<&dyn ValueType> {
+    None
+  }
+}
+
+/// Implemented by types which have a known value type. These types can be
+/// used as schema defaults with `SchemaBuilder::path`.
+pub trait HasValueType {
+  /// Returns the value type describing values of this type.
+  fn value_type() -> Box<dyn ValueType>;
+}
+
+/// A value type for any type which can be parsed with `FromStr`.
+pub struct FromStrType<T> {
+  name: &'static str,
+  marker: PhantomData<fn() -> T>,
+}
+
+impl<T: FromStr + IntoValue> FromStrType<T> {
+  /// Creates a new value type with the name used in error messages.
+  pub fn new(name: &'static str) -> Self {
+    Self {
+      name,
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<T> fmt::Debug for FromStrType<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_tuple("FromStrType").field(&self.name).finish()
+  }
+}
+
+impl<T: FromStr + IntoValue> ValueType for FromStrType<T> {
+  fn name(&self) -> &'static str {
+    self.name
+  }
+
+  fn parse(&self, raw: &str) -> Option<Value> {
+    raw.parse::<T>().ok().map(|v| v.into_value())
+  }
+}
+
+/// The value type of `bool`. `true`, `yes`, `on`, and `1` parse as `true`,
+/// and `false`, `no`, `off`, and `0` parse as `false`, ignoring case. A flag
+/// given without a value is `true`, and a negated flag is `false`.
+#[derive(Debug)]
+pub struct BoolType;
+
+impl ValueType for BoolType {
+  fn name(&self) -> &'static str {
+    "bool"
+  }
+
+  fn parse(&self, raw: &str) -> Option<Value> {
+    match raw.to_lowercase().as_str() {
+      "true" | "yes" | "on" | "1" => Some(Value::Bool(true)),
+      "false" | "no" | "off" | "0" => Some(Value::Bool(false)),
+      _ => None,
+    }
+  }
+
+  fn flag_value(&self) -> Option<Value> {
+    Some(Value::Bool(true))
+  }
+
+  fn negated_flag_value(&self) -> Option<Value> {
+    Some(Value::Bool(false))
+  }
+}
+
+/// The value type of `Value`. Any value is accepted as is, and raw strings
+/// are kept as strings.
+#[derive(Debug)]
+pub struct AnyType;
+
+impl ValueType for AnyType {
+  fn name(&self) -> &'static str {
+    "_"
+  }
+
+  fn parse(&self, raw: &str) -> Option<Value> {
+    Some(Value::String(raw.to_owned()))
+  }
+
+  fn cast(&self, value: Value) -> Option<Value> {
+    Some(value)
+  }
+}
+
+/// The value type of lists. Each item is cast with the item type. Raw strings
+/// are split on `,` and each item is parsed with the item type, so `a,b`
+/// becomes a list of two items and an empty string becomes an empty list.
+#[derive(Debug)]
+pub struct ListType {
+  item_type: Box<dyn ValueType>,
+}
+
+impl ListType {
+  /// Creates a new list type with the given item type.
+  pub fn new(item_type: Box<dyn ValueType>) -> Self {
+    Self { item_type }
+  }
+
+  /// Returns the value type of the items in the list.
+  pub fn item_type(&self) -> &dyn ValueType {
+    &*self.item_type
+  }
+}
+
+impl ValueType for ListType {
+  fn name(&self) -> &'static str {
+    "list"
+  }
+
+  fn parse(&self, raw: &str) -> Option<Value> {
+    if raw.is_empty() {
+      return Some(Value::Vec(Vec::new()));
+    }
+    raw
+      .split(',')
+      .map(|item| self.item_type.parse(item.trim()))
+      .collect::<Option<_>>()
+      .map(Value::Vec)
+  }
+
+  fn cast(&self, value: Value) -> Option<Value> {
+    match value {
+      Value::Vec(items) => items
+        .into_iter()
+        .map(|v| self.item_type.cast(v))
+        .collect::<Option<_>>()
+        .map(Value::Vec),
+      _ => None,
+    }
+  }
+
+  fn item_type(&self) -> Option<&dyn ValueType> {
+    Some(&*self.item_type)
+  }
+}
+
+/// The value type of maps with string keys. Each value is cast with the
+/// value type of the map. Raw strings are split on `,` into `key=value`
+/// entries, and each value is parsed with the value type of the map, so
+/// `env=prod,team=core` becomes a map with two entries.
+#[derive(Debug)]
+pub struct MapType {
+  value_type: Box<dyn ValueType>,
+}
+
+impl MapType {
+  /// Creates a new map type with the given type for its values.
+  pub fn new(value_type: Box<dyn ValueType>) -> Self {
+    Self { value_type }
+  }
+
+  /// Returns the value type of the values in the map.
+  pub fn value_type(&self) -> &dyn ValueType {
+    &*self.value_type
+  }
+}
+
+impl ValueType for MapType {
+  fn name(&self) -> &'static str {
+    "map"
+  }
+
+  fn parse(&self, raw: &str) -> Option<Value> {
+    if raw.is_empty() {
+      return Some(Value::HashMap(HashMap::new()));
+    }
+    raw
+      .split(',')
+      .map(|entry| {
+        let mut entry = entry.splitn(2, '=');
+        let key = entry.next()?.trim();
+        let value = self.value_type.parse(entry.next()?.trim())?;
+        Some((key.to_owned(), value))
+      })
+      .collect::<Option<HashMap<_, _>>>()
+      .map(Value::HashMap)
+  }
+
+  fn cast(&self, value: Value) -> Option<Value> {
+    match value {
+      Value::HashMap(entries) => entries
+        .into_iter()
+        .map(|(k, v)| Some((k, self.value_type.cast(v)?)))
+        .collect::<Option<HashMap<_, _>>>()
+        .map(Value::HashMap),
+      _ => None,
+    }
+  }
+
+  fn entry_type(&self) -> Option<&dyn ValueType> {
+    Some(&*self.value_type)
+  }
+}
+
+macro_rules! define_from_str_types {
+  ($($type:ident),*) => {
+    $(
+      impl HasValueType for $type {
+        fn value_type() -> Box<dyn ValueType> {
+          Box::new(FromStrType::<$type>::new(stringify!($type)))
+        }
+      }
+    )*
+  };
+}
+
+define_from_str_types!(usize, isize, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, String);
+#[cfg(feature = "int128")]
+define_from_str_types!(u128, i128);
+
+impl HasValueType for bool {
+  fn value_type() -> Box<dyn ValueType> {
+    Box::new(BoolType)
+  }
+}
+
+impl HasValueType for &'static str {
+  fn value_type() -> Box<dyn ValueType> {
+    String::value_type()
+  }
+}
+
+impl HasValueType for Value {
+  fn value_type() -> Box<dyn ValueType> {
+    Box::new(AnyType)
+  }
+}
+
+impl<T: HasValueType> HasValueType for Vec<T> {
+  fn value_type() -> Box<dyn ValueType> {
+    Box::new(ListType::new(T::value_type()))
+  }
+}
+
+impl<T: HasValueType> HasValueType for HashMap<String, T> {
+  fn value_type() -> Box<dyn ValueType> {
+    Box::new(MapType::new(T::value_type()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn can_parse_raw_values() {
+    assert_eq!(u16::value_type().parse("8000"), Some(Value::U16(8000)));
+    assert_eq!(u16::value_type().parse("-1"), None);
+    assert_eq!(bool::value_type().flag_value(), Some(Value::Bool(true)));
+  }
+
+  #[test]
+  fn can_parse_bools() {
+    let value_type = bool::value_type();
+
+    for raw in &["true", "Yes", "ON", "1"] {
+      assert_eq!(value_type.parse(raw), Some(Value::Bool(true)));
+    }
+    for raw in &["false", "No", "OFF", "0"] {
+      assert_eq!(value_type.parse(raw), Some(Value::Bool(false)));
+    }
+    assert_eq!(value_type.parse("maybe"), None);
+  }
+
+  #[test]
+  fn can_cast_list_items() {
+    let value_type = Vec::<u32>::value_type();
+
+    let value = value_type.cast(Value::Vec(vec![Value::I64(1), Value::String("2".into())]));
+
+    assert_eq!(value, Some(Value::Vec(vec![Value::U32(1), Value::U32(2)])));
+  }
+
+  #[test]
+  fn can_parse_key_value_maps() {
+    let value_type = HashMap::<String, String>::value_type();
+
+    let value = value_type.parse("env=prod, team=core");
+
+    let mut expected = HashMap::new();
+    expected.insert("env".to_owned(), Value::String("prod".into()));
+    expected.insert("team".to_owned(), Value::String("core".into()));
+    assert_eq!(value, Some(Value::HashMap(expected)));
+    assert_eq!(value_type.parse("env"), None);
+  }
+
+  #[test]
+  fn can_parse_delimited_lists() {
+    let value_type = Vec::<String>::value_type();
+
+    let value = value_type.parse("a, b");
+
+    assert_eq!(value, Some(Value::Vec(vec![Value::String("a".into()), Value::String("b".into())])));
+    assert_eq!(Vec::<u8>::value_type().parse("1,x"), None);
+  }
+}
@@ -0,0 +1,122 @@
+use syn::{Attribute, Error, Ident, LitStr, Path, Result};
+
+/// The `#[unison(...)]` attributes of a struct or enum.
+#[derive(Default)]
+pub struct ContainerAttrs {
+  pub rename_all: Option<RenameRule>,
+}
+
+impl ContainerAttrs {
+  pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+    let mut container_attrs = Self::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("unison")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("rename_all") {
+          let rule: LitStr = meta.value()?.parse()?;
+          container_attrs.rename_all = Some(RenameRule::parse(&rule)?);
+          Ok(())
+        } else {
+          Err(meta.error("unknown unison container attribute"))
+        }
+      })?;
+    }
+    Ok(container_attrs)
+  }
+}
+
+/// The `#[unison(...)]` attributes of a field or enum variant.
+#[derive(Default)]
+pub struct FieldAttrs {
+  pub rename: Option<String>,
+  pub default: Option<FieldDefault>,
+  pub flatten: bool,
+}
+
+pub enum FieldDefault {
+  Trait,
+  Function(Path),
+}
+
+impl FieldAttrs {
+  pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+    let mut field_attrs = Self::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("unison")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("rename") {
+          let name: LitStr = meta.value()?.parse()?;
+          field_attrs.rename = Some(name.value());
+        } else if meta.path.is_ident("default") {
+          field_attrs.default = Some(if meta.input.peek(syn::Token![=]) {
+            let function: LitStr = meta.value()?.parse()?;
+            FieldDefault::Function(function.parse()?)
+          } else {
+            FieldDefault::Trait
+          });
+        } else if meta.path.is_ident("flatten") {
+          field_attrs.flatten = true;
+        } else {
+          return Err(meta.error("unknown unison field attribute"));
+        }
+        Ok(())
+      })?;
+    }
+    Ok(field_attrs)
+  }
+
+  /// Returns the key used for the field or variant within a value tree.
+  pub fn key(&self, ident: &Ident, rename_all: Option<RenameRule>) -> String {
+    if let Some(ref rename) = self.rename {
+      return rename.to_owned();
+    }
+    let name = ident.to_string();
+    let name = name.trim_start_matches("r#");
+    match rename_all {
+      Some(rule) => rule.apply(name),
+      None => name.to_owned(),
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+  Lowercase,
+  Uppercase,
+  SnakeCase,
+  KebabCase,
+}
+
+impl RenameRule {
+  fn parse(rule: &LitStr) -> Result<Self> {
+    match rule.value().as_str() {
+      "lowercase" => Ok(RenameRule::Lowercase),
+      "UPPERCASE" => Ok(RenameRule::Uppercase),
+      "snake_case" => Ok(RenameRule::SnakeCase),
+      "kebab-case" => Ok(RenameRule::KebabCase),
+      _ => Err(Error::new(rule.span(), "unknown rename rule")),
+    }
+  }
+
+  fn apply(&self, name: &str) -> String {
+    match *self {
+      RenameRule::Lowercase => name.to_lowercase(),
+      RenameRule::Uppercase => name.to_uppercase(),
+      RenameRule::SnakeCase => to_snake_case(name),
+      RenameRule::KebabCase => to_snake_case(name).replace('_', "-"),
+    }
+  }
+}
+
+fn to_snake_case(name: &str) -> String {
+  let mut snake_case = String::new();
+  for (i, c) in name.chars().enumerate() {
+    if c.is_uppercase() {
+      if i > 0 && !snake_case.ends_with('_') {
+        snake_case.push('_');
+      }
+      snake_case.extend(c.to_lowercase());
+    } else {
+      snake_case.push(c);
+    }
+  }
+  snake_case
+}
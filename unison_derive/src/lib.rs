@@ -0,0 +1,61 @@
+//! Derive macros for the `FromValue`, `IntoValue`, and `UnisonSchema` traits
+//! of unison. These are re-exported by unison when its `derive` feature is
+//! enabled.
+//!
+//! Structs with named fields are stored as hash maps keyed by field name,
+//! newtype structs are stored as their inner value, and enums with only unit
+//! variants are stored as strings of the variant name. The following
+//! attributes are supported:
+//!
+//! - `#[unison(rename_all = "...")]` on a struct or enum renames every field
+//!   or variant. One of `lowercase`, `UPPERCASE`, `snake_case`, or
+//!   `kebab-case`.
+//! - `#[unison(rename = "...")]` on a field or variant sets its key.
+//! - `#[unison(default)]` on a field uses `Default::default()` when the field
+//!   is missing. `#[unison(default = "path::to::fn")]` calls the given
+//!   function instead.
+//! - `#[unison(flatten)]` on a field reads and writes the field's own fields
+//!   from the containing hash map.
+//!
+//! `UnisonSchema` adds a schema path for every field of a struct, nested by
+//! field, so `port` within a `server` field becomes `server.port`. Every field
+//! type must implement `UnisonSchema` itself. Defaults come from the struct's
+//! `Default` implementation, or from a field's `default` attribute. `rename`,
+//! `rename_all`, and `flatten` apply to paths as they do to keys.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+mod attr;
+mod schema;
+mod value;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+#[proc_macro_derive(FromValue, attributes(unison))]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  value::derive_from_value(&input)
+    .unwrap_or_else(|e| e.to_compile_error())
+    .into()
+}
+
+#[proc_macro_derive(IntoValue, attributes(unison))]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  value::derive_into_value(&input)
+    .unwrap_or_else(|e| e.to_compile_error())
+    .into()
+}
+
+#[proc_macro_derive(UnisonSchema, attributes(unison))]
+pub fn derive_unison_schema(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  schema::derive_unison_schema(&input)
+    .unwrap_or_else(|e| e.to_compile_error())
+    .into()
+}
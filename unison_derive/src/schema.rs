@@ -0,0 +1,63 @@
+use proc_macro2::TokenStream;
+use syn::{Data, DeriveInput, Error, Fields, Result};
+use attr::{ContainerAttrs, FieldAttrs, FieldDefault};
+
+pub fn derive_unison_schema(input: &DeriveInput) -> Result<TokenStream> {
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let container_attrs = ContainerAttrs::parse(&input.attrs)?;
+
+  let fields = match input.data {
+    Data::Struct(ref data) => match data.fields {
+      Fields::Named(ref fields) => &fields.named,
+      _ => return Err(Error::new_spanned(ident, "UnisonSchema can only be derived for structs with named fields")),
+    },
+    _ => return Err(Error::new_spanned(ident, "UnisonSchema can only be derived for structs")),
+  };
+
+  let mut field_idents = Vec::new();
+  let mut add_paths = Vec::new();
+  for field in fields.iter() {
+    let field_ident = field.ident.as_ref().unwrap();
+    let field_ty = &field.ty;
+    let field_attrs = FieldAttrs::parse(&field.attrs)?;
+
+    let path = if field_attrs.flatten {
+      quote! { path_name.to_owned() }
+    } else {
+      let key = field_attrs.key(field_ident, container_attrs.rename_all);
+      quote! {
+        if path_name.is_empty() {
+          #key.to_owned()
+        } else {
+          format!("{}.{}", path_name, #key)
+        }
+      }
+    };
+    let default_value = match field_attrs.default {
+      Some(FieldDefault::Trait) => quote! { ::std::default::Default::default() },
+      Some(FieldDefault::Function(ref function)) => quote! { #function() },
+      None => quote! { #field_ident },
+    };
+
+    field_idents.push(field_ident);
+    add_paths.push(quote! {
+      let builder = <#field_ty as ::unison::UnisonSchema>::add_schema_paths(builder, &#path, #default_value);
+    });
+  }
+
+  Ok(quote! {
+    impl #impl_generics ::unison::UnisonSchema for #ident #ty_generics #where_clause {
+      #[allow(unused_variables)]
+      fn add_schema_paths(
+        builder: ::unison::SchemaBuilder,
+        path_name: &str,
+        default_value: Self,
+      ) -> ::unison::SchemaBuilder {
+        let #ident { #(#field_idents,)* } = default_value;
+        #(#add_paths)*
+        builder
+      }
+    }
+  })
+}
@@ -0,0 +1,165 @@
+use proc_macro2::TokenStream;
+use syn::{Data, DeriveInput, Error, Fields, Result};
+use attr::{ContainerAttrs, FieldAttrs, FieldDefault};
+
+pub fn derive_from_value(input: &DeriveInput) -> Result<TokenStream> {
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let container_attrs = ContainerAttrs::parse(&input.attrs)?;
+
+  let body = match input.data {
+    Data::Struct(ref data) => match data.fields {
+      Fields::Named(ref fields) => {
+        let not_map_message = format!("Cannot cast non hash map value into `{}`.", ident);
+        let mut field_values = Vec::new();
+        let mut flattened_field_values = Vec::new();
+        for field in fields.named.iter() {
+          let field_ident = field.ident.as_ref().unwrap();
+          let field_attrs = FieldAttrs::parse(&field.attrs)?;
+          if field_attrs.flatten {
+            flattened_field_values.push(quote! {
+              #field_ident: ::unison::FromValue::from_value(
+                ::unison::Value::HashMap(hash_map.clone())
+              )?
+            });
+            continue;
+          }
+
+          let key = field_attrs.key(field_ident, container_attrs.rename_all);
+          let missing_value = match field_attrs.default {
+            Some(FieldDefault::Trait) => quote! { ::std::default::Default::default() },
+            Some(FieldDefault::Function(ref function)) => quote! { #function() },
+            None => quote! {
+              ::unison::FromValue::from_value(::unison::Value::None)
+                .map_err(|_| ::unison::FromValueError::MissingField(#key.to_owned()))?
+            },
+          };
+          field_values.push(quote! {
+            #field_ident: match hash_map.remove(#key) {
+              Some(::unison::Value::None) | None => #missing_value,
+              Some(value) => ::unison::FromValue::from_value(value)?,
+            }
+          });
+        }
+        quote! {
+          let mut hash_map = match value {
+            ::unison::Value::HashMap(hash_map) => hash_map,
+            _ => return Err(::unison::FromValueError::invalid_cast(#not_map_message)),
+          };
+          Ok(#ident {
+            #(#field_values,)*
+            #(#flattened_field_values,)*
+          })
+        }
+      }
+      Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => quote! {
+        Ok(#ident(::unison::FromValue::from_value(value)?))
+      },
+      _ => {
+        return Err(Error::new_spanned(
+          ident,
+          "FromValue can only be derived for structs with named fields or newtype structs",
+        ))
+      }
+    },
+    Data::Enum(ref data) => {
+      let not_string_message = format!("Cannot cast non-string value into `{}`.", ident);
+      let mut variants = Vec::new();
+      for variant in data.variants.iter() {
+        if !variant.fields.is_empty() {
+          return Err(Error::new_spanned(variant, "FromValue can only be derived for unit variants"));
+        }
+        let variant_ident = &variant.ident;
+        let key = FieldAttrs::parse(&variant.attrs)?.key(variant_ident, container_attrs.rename_all);
+        variants.push(quote! { #key => Ok(#ident::#variant_ident) });
+      }
+      quote! {
+        match value {
+          ::unison::Value::String(string) => match string.as_str() {
+            #(#variants,)*
+            _ => Err(::unison::FromValueError::UnknownVariant(string.to_owned())),
+          },
+          _ => Err(::unison::FromValueError::invalid_cast(#not_string_message)),
+        }
+      }
+    }
+    Data::Union(_) => return Err(Error::new_spanned(ident, "FromValue cannot be derived for unions")),
+  };
+
+  Ok(quote! {
+    impl #impl_generics ::unison::FromValue for #ident #ty_generics #where_clause {
+      fn from_value(value: ::unison::Value) -> ::std::result::Result<Self, ::unison::FromValueError> {
+        #body
+      }
+    }
+  })
+}
+
+pub fn derive_into_value(input: &DeriveInput) -> Result<TokenStream> {
+  let ident = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  let container_attrs = ContainerAttrs::parse(&input.attrs)?;
+
+  let body = match input.data {
+    Data::Struct(ref data) => match data.fields {
+      Fields::Named(ref fields) => {
+        let mut inserts = Vec::new();
+        for field in fields.named.iter() {
+          let field_ident = field.ident.as_ref().unwrap();
+          let field_attrs = FieldAttrs::parse(&field.attrs)?;
+          if field_attrs.flatten {
+            inserts.push(quote! {
+              if let ::unison::Value::HashMap(flattened) = ::unison::IntoValue::into_value(self.#field_ident) {
+                hash_map.extend(flattened);
+              }
+            });
+            continue;
+          }
+          let key = field_attrs.key(field_ident, container_attrs.rename_all);
+          inserts.push(quote! {
+            hash_map.insert(#key.to_owned(), ::unison::IntoValue::into_value(self.#field_ident));
+          });
+        }
+        quote! {
+          let mut hash_map = ::std::collections::HashMap::new();
+          #(#inserts)*
+          ::unison::Value::HashMap(hash_map)
+        }
+      }
+      Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => quote! {
+        ::unison::IntoValue::into_value(self.0)
+      },
+      _ => {
+        return Err(Error::new_spanned(
+          ident,
+          "IntoValue can only be derived for structs with named fields or newtype structs",
+        ))
+      }
+    },
+    Data::Enum(ref data) => {
+      let mut variants = Vec::new();
+      for variant in data.variants.iter() {
+        if !variant.fields.is_empty() {
+          return Err(Error::new_spanned(variant, "IntoValue can only be derived for unit variants"));
+        }
+        let variant_ident = &variant.ident;
+        let key = FieldAttrs::parse(&variant.attrs)?.key(variant_ident, container_attrs.rename_all);
+        variants.push(quote! { #ident::#variant_ident => ::unison::Value::String(#key.to_owned()) });
+      }
+      quote! {
+        match self {
+          #(#variants,)*
+        }
+      }
+    }
+    Data::Union(_) => return Err(Error::new_spanned(ident, "IntoValue cannot be derived for unions")),
+  };
+
+  Ok(quote! {
+    impl #impl_generics ::unison::IntoValue for #ident #ty_generics #where_clause {
+      fn into_value(self) -> ::unison::Value {
+        #body
+      }
+    }
+  })
+}
@@ -0,0 +1,117 @@
+extern crate unison;
+#[macro_use]
+extern crate unison_derive;
+
+use std::collections::HashMap;
+use unison::FromValue as _;
+use unison::IntoValue as _;
+use unison::{FromValueError, Value};
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+struct Server {
+  port: u32,
+  #[unison(rename = "hostname")]
+  host: String,
+  #[unison(default)]
+  peers: Vec<String>,
+  #[unison(default = "default_mode")]
+  mode: Mode,
+  timeout: Option<u32>,
+  #[unison(flatten)]
+  tls: Tls,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+struct Tls {
+  tls_cert: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+#[unison(rename_all = "kebab-case")]
+enum Mode {
+  Fast,
+  ExtraSafe,
+  #[unison(rename = "off")]
+  Disabled,
+}
+
+#[derive(Debug, PartialEq, FromValue, IntoValue)]
+struct Port(u16);
+
+fn default_mode() -> Mode {
+  Mode::Fast
+}
+
+fn server_value() -> Value {
+  let mut value = Value::HashMap(HashMap::new());
+  value.set_path("port", Value::U32(8000));
+  value.set_path("hostname", Value::String("localhost".into()));
+  value.set_path("tls_cert", Value::String("cert.pem".into()));
+  value
+}
+
+#[test]
+fn can_derive_from_value_for_struct() {
+  let server: Server = server_value().into_type().unwrap();
+
+  assert_eq!(
+    server,
+    Server {
+      port: 8000,
+      host: "localhost".into(),
+      peers: Vec::new(),
+      mode: Mode::Fast,
+      timeout: None,
+      tls: Tls {
+        tls_cert: Some("cert.pem".into()),
+      },
+    }
+  );
+}
+
+#[test]
+fn can_derive_into_value_for_struct() {
+  let server = Server {
+    port: 8000,
+    host: "localhost".into(),
+    peers: vec!["a".into()],
+    mode: Mode::ExtraSafe,
+    timeout: Some(30),
+    tls: Tls { tls_cert: None },
+  };
+
+  let value = server.into_value();
+
+  assert_eq!(*value.get_path("hostname"), Value::String("localhost".into()));
+  assert_eq!(*value.get_path("mode"), Value::String("extra-safe".into()));
+  assert_eq!(*value.get_path("timeout"), Value::U32(30));
+  assert_eq!(*value.get_path("tls_cert"), Value::None);
+  assert_eq!(Server::from_value(value).unwrap().mode, Mode::ExtraSafe);
+}
+
+#[test]
+fn cannot_derive_from_value_with_missing_field() {
+  let mut value = server_value();
+  value.set_path("port", Value::None);
+
+  match Server::from_value(value) {
+    Err(FromValueError::MissingField(ref field)) => assert_eq!(field, "port"),
+    r => panic!("unexpected result {:?}", r),
+  }
+}
+
+#[test]
+fn can_derive_unit_variant_enums_as_strings() {
+  assert_eq!(Mode::from_value(Value::String("off".into())).unwrap(), Mode::Disabled);
+  assert_eq!(Mode::Disabled.into_value(), Value::String("off".into()));
+  match Mode::from_value(Value::String("slow".into())) {
+    Err(FromValueError::UnknownVariant(ref variant)) => assert_eq!(variant, "slow"),
+    r => panic!("unexpected result {:?}", r),
+  }
+}
+
+#[test]
+fn can_derive_newtype_structs() {
+  assert_eq!(Port::from_value(Value::U16(80)).unwrap(), Port(80));
+  assert_eq!(Port(80).into_value(), Value::U16(80));
+}
@@ -0,0 +1,69 @@
+extern crate unison;
+#[macro_use]
+extern crate unison_derive;
+
+use unison::UnisonSchema as _;
+use unison::Value;
+
+#[derive(Default, UnisonSchema)]
+struct AppConfig {
+  server: Server,
+  #[unison(rename = "log-level", default = "default_log_level")]
+  log_level: String,
+  #[unison(flatten)]
+  limits: Limits,
+  proxy: Option<String>,
+}
+
+#[derive(UnisonSchema)]
+struct Server {
+  port: u16,
+  peers: Vec<String>,
+}
+
+impl Default for Server {
+  fn default() -> Self {
+    Self {
+      port: 8000,
+      peers: vec!["a".into()],
+    }
+  }
+}
+
+#[derive(Default, UnisonSchema)]
+struct Limits {
+  max_connections: u32,
+}
+
+fn default_log_level() -> String {
+  "info".into()
+}
+
+#[test]
+fn can_derive_schema_paths_from_field_nesting() {
+  let schema = AppConfig::schema();
+
+  assert!(schema.path_is_type::<u16>("server.port"));
+  assert!(schema.path_is_type::<Vec<String>>("server.peers"));
+  assert!(schema.path_is_type::<String>("log-level"));
+  assert!(schema.path_is_type::<u32>("max_connections"));
+}
+
+#[test]
+fn can_derive_schema_defaults_from_default_and_attributes() {
+  let schema = AppConfig::schema();
+
+  assert_eq!(*schema.path_default::<u16>("server.port").unwrap(), 8000);
+  assert_eq!(*schema.path_default::<String>("log-level").unwrap(), "info");
+  assert_eq!(*schema.defaults().get_path("server.peers"), Value::Vec(vec![Value::String("a".into())]));
+}
+
+#[test]
+fn can_derive_optional_paths_without_defaults() {
+  let schema = AppConfig::schema();
+
+  assert!(schema.path_is_type::<String>("proxy"));
+  assert!(!schema.path_meta("proxy").unwrap().required);
+  assert_eq!(schema.path_default::<String>("proxy"), None);
+  assert_eq!(*schema.defaults().get_path("proxy"), Value::None);
+}